@@ -1,39 +1,635 @@
-use abi_stable::{sabi_trait::TD_Opaque, std_types::Tuple2};
+use abi_stable::{
+    sabi_trait::TD_Opaque,
+    std_types::{ROption, RSlice, RStr, Tuple2},
+};
 
+use crate::{
+    plugins::{self, LoadError},
+    profile::{self, Profile},
+    target::Target,
+};
+use clap::ValueEnum;
 use futures::stream::Stream;
-use interface::{ConnectionPlugin_Ref, ConnectionTimerBox, load_root_module_from_file};
+use interface::{ConnectionPlugin_Ref, ConnectionTimerBox, ErrorKind, SocketAddress, TestInfo};
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
+    fmt::Display,
     net::SocketAddr,
     pin::Pin,
-    sync::Arc,
-    task::{Poll, ready},
-    time::Instant,
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    task::{Context, Poll, Waker, ready},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+    task::JoinSet,
+    time::MissedTickBehavior,
 };
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
 
 #[derive(Debug)]
 pub enum Message {
-    Data(u64),
+    Data(Sample),
+    Failed(Failure),
     Pause,
     Resume,
     Stop,
     ConnectionEnded,
 }
 
+/// request reported as failed by the plugin
+#[derive(Clone, Debug, PartialEq)]
+pub struct Failure {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// reported by the plugin before the request failed
+    pub metrics: Vec<(String, Metric)>,
+    /// finished during the warm-up, left out of the results
+    pub warmup: bool,
+    /// number of the connection in the order they were spawned
+    pub connection: u64,
+    /// wall clock time the request started, the time the failure was reported
+    /// when the connection failed outside of a request
+    pub started: SystemTime,
+}
+
+/// successful request
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub latency: Duration,
+    /// parts of the request ended by the plugin marks, in order, the last
+    /// one is named `stop`, empty when the plugin doesn't use marks
+    pub phases: Vec<(String, Duration)>,
+    /// values reported by the plugin during the request
+    pub metrics: Vec<(String, Metric)>,
+    /// finished during the warm-up, left out of the results
+    pub warmup: bool,
+    /// number of the connection in the order they were spawned
+    pub connection: u64,
+    /// wall clock time the request started
+    pub started: SystemTime,
+}
+
+/// numeric value reported by the plugin
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    /// summed, e.g. bytes sent
+    Counter(u64),
+    /// averaged, e.g. size of the connection pool
+    Gauge(f64),
+}
+
+/// result of a single request
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Success(Sample),
+    Failure(Failure),
+}
+
+impl Outcome {
+    /// sent by the increase mode after reaching max connections and by the
+    /// profile mode after the last stage
+    pub const FINISHED: Outcome = Outcome::Success(Sample {
+        latency: Duration::MAX,
+        phases: Vec::new(),
+        metrics: Vec::new(),
+        warmup: false,
+        connection: 0,
+        started: SystemTime::UNIX_EPOCH,
+    });
+
+    /// finished during the warm-up
+    pub fn warmup(&self) -> bool {
+        match self {
+            Outcome::Success(sample) => sample.warmup,
+            Outcome::Failure(failure) => failure.warmup,
+        }
+    }
+}
+
+/// deadlines enforced by the host on connections spawned from the plugin
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timeouts {
+    /// time between `start` and `stop` of a single request
+    pub request: Option<Duration>,
+    /// lifetime of the whole `run_connection` future
+    pub connection: Option<Duration>,
+    /// time the requests in progress get to end after stop, they're aborted
+    /// right away without it
+    pub drain: Option<Duration>,
+}
+
+/// how the load is driven, also written to the exported results
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Constant,
+    Increase,
+    /// open-loop mode starting requests at a constant rate
+    Rate,
+    /// connections following the stages of the load profile
+    Profile,
+}
+
+/// start of the test driven normally but left out of the results
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Warmup {
+    Duration(Duration),
+    /// number of finished requests, failed ones included
+    Requests(u64),
+}
+
+impl Display for Warmup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warmup::Duration(duration) => write!(f, "{}", profile::format_duration(*duration)),
+            Warmup::Requests(requests) => write!(f, "{requests} req"),
+        }
+    }
+}
+
+impl FromStr for Warmup {
+    type Err = String;
+
+    /// `30s`, `2m` or `500 req`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        match s.strip_suffix("req") {
+            Some(requests) => requests
+                .trim()
+                .parse()
+                .map(Warmup::Requests)
+                .map_err(|_| format!("bad requests number `{s}`")),
+            None => profile::parse_duration(s).map(Warmup::Duration),
+        }
+    }
+}
+
+/// ends the constant and rate modes once either limit is reached, both are
+/// counted after the warm-up
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub duration: Option<Duration>,
+    /// finished requests, failed ones included
+    pub requests: Option<u64>,
+}
+
+/// tags the results finished during the warm-up and tells when the limits
+/// are reached, time spent paused isn't counted
+#[derive(Debug)]
+struct Progress {
+    warmup: Option<Warmup>,
+    limits: Limits,
+    requests: u64,
+    /// requests finished after the warm-up
+    measured_requests: u64,
+    /// time since the start when the warm-up ended
+    measured_from: Option<Duration>,
+    /// time before the last pause
+    elapsed: Duration,
+    /// none while paused
+    resumed: Option<Instant>,
+    /// limits were reached, waiting for the requests in progress
+    draining_since: Option<Instant>,
+    /// connections still running this long after the limit are aborted,
+    /// right away without it
+    drain: Option<Duration>,
+}
+
+impl Progress {
+    /// `drain` is the stop drain timeout of the test
+    fn new(warmup: Option<Warmup>, limits: Limits, drain: Option<Duration>) -> Self {
+        // the end of the requests warm-up is known once it's over
+        let measured_from = match warmup {
+            None | Some(Warmup::Requests(0)) => Some(Duration::ZERO),
+            Some(Warmup::Duration(duration)) => Some(duration),
+            Some(Warmup::Requests(_)) => None,
+        };
+
+        Progress {
+            warmup,
+            limits,
+            requests: 0,
+            measured_requests: 0,
+            measured_from,
+            elapsed: Duration::ZERO,
+            resumed: Some(Instant::now()),
+            draining_since: None,
+            drain,
+        }
+    }
+    fn pause(&mut self) {
+        if let Some(resumed) = self.resumed.take() {
+            self.elapsed += resumed.elapsed();
+        }
+    }
+    fn resume(&mut self) {
+        self.resumed.get_or_insert_with(Instant::now);
+    }
+    fn elapsed(&self) -> Duration {
+        self.elapsed + self.resumed.map_or(Duration::ZERO, |at| at.elapsed())
+    }
+    /// counts the result, true when it belongs to the warm-up
+    fn tag(&mut self) -> bool {
+        let elapsed = self.elapsed();
+
+        let warmup = match self.warmup {
+            None => false,
+            Some(Warmup::Duration(duration)) => elapsed < duration,
+            Some(Warmup::Requests(requests)) => self.requests < requests,
+        };
+        self.requests += 1;
+
+        if !warmup {
+            self.measured_requests += 1;
+            self.measured_from.get_or_insert(elapsed);
+        }
+
+        warmup
+    }
+    fn draining(&self) -> bool {
+        self.draining_since.is_some()
+    }
+    /// starts draining once a limit is reached, true while draining
+    fn check_limits(&mut self) -> bool {
+        let requests_reached = self
+            .limits
+            .requests
+            .is_some_and(|requests| self.measured_requests >= requests);
+        let duration_reached = self.limits.duration.is_some_and(|duration| {
+            self.measured_from
+                .is_some_and(|from| self.elapsed() >= from + duration)
+        });
+
+        if requests_reached || duration_reached {
+            self.draining_since.get_or_insert_with(Instant::now);
+        }
+
+        self.draining()
+    }
+    /// the requests in progress ended or took too long
+    fn drained(&self, in_progress: u64) -> bool {
+        self.draining_since.is_some_and(|since| {
+            in_progress == 0 || since.elapsed() >= self.drain.unwrap_or_default()
+        })
+    }
+    /// part of the drain timeout left once the test stops, the drain starts
+    /// when the limits are reached
+    fn drain_left(&self) -> Option<Duration> {
+        match self.draining_since {
+            Some(since) => self
+                .drain
+                .map(|drain| drain.saturating_sub(since.elapsed())),
+            None => self.drain,
+        }
+    }
+    /// when the loop has to wake up to check the limits without new results
+    fn deadline(&self) -> Option<Instant> {
+        if let Some(since) = self.draining_since {
+            return Some(since + self.drain.unwrap_or_default());
+        }
+
+        let resumed = self.resumed?;
+        let end = self.measured_from? + self.limits.duration?;
+
+        Some(resumed + end.saturating_sub(self.elapsed))
+    }
+}
+
+/// closed while the test is paused, connections between requests are held
+/// by `Gated` until it opens, checking the open gate is a single atomic load
+#[derive(Debug)]
+struct PauseGate {
+    paused: AtomicBool,
+    /// tasks of the held connections
+    waiting: Mutex<Vec<Waker>>,
+    /// the pause times are counted in nanoseconds since it
+    created: Instant,
+    closed_at: AtomicU64,
+    /// time spent paused before the last close
+    paused_before: AtomicU64,
+}
+
+impl Default for PauseGate {
+    fn default() -> Self {
+        PauseGate {
+            paused: AtomicBool::new(false),
+            waiting: Mutex::default(),
+            created: Instant::now(),
+            closed_at: AtomicU64::new(0),
+            paused_before: AtomicU64::new(0),
+        }
+    }
+}
+
+impl PauseGate {
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+    fn now(&self) -> u64 {
+        self.created.elapsed().as_nanos() as u64
+    }
+    fn close(&self) {
+        if !self.is_paused() {
+            self.closed_at.store(self.now(), Ordering::Release);
+            self.paused.store(true, Ordering::Release);
+        }
+    }
+    fn open(&self) {
+        if !self.is_paused() {
+            return;
+        }
+
+        let paused = self.now() - self.closed_at.load(Ordering::Acquire);
+        self.paused_before.fetch_add(paused, Ordering::AcqRel);
+        self.paused.store(false, Ordering::Release);
+
+        for waker in self.waiting.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+    /// time the gate was closed so far, the pause in progress included
+    fn paused_time(&self) -> Duration {
+        let mut paused = self.paused_before.load(Ordering::Acquire);
+        if self.is_paused() {
+            paused += self.now() - self.closed_at.load(Ordering::Acquire);
+        }
+
+        Duration::from_nanos(paused)
+    }
+    /// ready once the gate is open, the task is woken by `open` otherwise
+    fn poll_open(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if !self.is_paused() {
+            return Poll::Ready(());
+        }
+
+        self.waiting.lock().unwrap().push(cx.waker().clone());
+
+        // opened before the waker was registered
+        if self.is_paused() {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+/// connection future that isn't polled while the test is paused and no
+/// request is in progress, `start` is synchronous so it can't wait itself
+#[pin_project]
+struct Gated<F> {
+    #[pin]
+    task: F,
+    gate: Arc<PauseGate>,
+    request_start: RequestStart,
+}
+
+impl<F: Future> Future for Gated<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        // requests already in progress are finished
+        if this.gate.is_paused() && this.request_start.lock().unwrap().is_none() {
+            ready!(this.gate.poll_open(cx));
+        }
+
+        this.task.poll(cx)
+    }
+}
+
+/// connections spawned by the test
+#[derive(Debug, Default)]
+struct Connections {
+    tasks: JoinSet<()>,
+    gate: Arc<PauseGate>,
+    /// number of the last spawned connection, they're numbered from 1
+    last_id: u64,
+    /// id of the test given to the plugin
+    test: u64,
+}
+
+impl Connections {
+    fn new(test: u64) -> Self {
+        Connections {
+            test,
+            ..Default::default()
+        }
+    }
+    /// forgets the connections that ended
+    fn reap(&mut self) {
+        while self.tasks.try_join_next().is_some() {}
+    }
+    fn pause(&self) {
+        self.gate.close();
+    }
+    fn resume(&self) {
+        self.gate.open();
+    }
+    /// waits up to `drain` for the connections to end and aborts the rest
+    async fn stop(mut self, drain: Option<Duration>) {
+        // connections waiting for resume would never end
+        self.gate.open();
+
+        if let Some(drain) = drain {
+            let _ = tokio::time::timeout(drain, async {
+                while self.tasks.join_next().await.is_some() {}
+            })
+            .await;
+        }
+
+        self.tasks.shutdown().await;
+    }
+}
+
+/// waits forever without the deadline
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// settings shared by all test modes
+#[derive(Clone, Debug)]
+pub struct TestConfig {
+    pub target: Target,
+    pub plugins_path: String,
+    pub plugin_name: String,
+    pub timeouts: Timeouts,
+    pub parameters: Arc<Parameters>,
+    pub warmup: Option<Warmup>,
+    pub limits: Limits,
+}
+
+/// values of the parameters declared by the plugin, by name
+pub type Parameters = BTreeMap<String, String>;
+
+/// start of the request in progress, shared between the timer and the watchdog
+type RequestStart = Arc<Mutex<Option<Instant>>>;
+
+/// stream of test data together with the channel controlling the test
+pub type TestHandle<T> = Arc<(Wrapper<T>, UnboundedSender<Message>)>;
+
 #[derive(Debug)]
 pub struct ConnectionManager {}
 
 impl ConnectionManager {
+    /// loads the plugin and lets it prepare for the test, returns the id of
+    /// the test that tells it apart from the others sharing the library
+    fn start_plugin(config: &TestConfig) -> Result<(ConnectionPlugin_Ref, u64), LoadError> {
+        static LAST_TEST: AtomicU64 = AtomicU64::new(0);
+
+        // rebuilt plugins are picked up by every new test
+        let plugin = plugins::load(&config.plugins_path, &config.plugin_name)
+            .map_err(|err| LoadError::from(&err))?;
+        let test = LAST_TEST.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if let Some(on_test_start) = plugin.on_test_start() {
+            let addresses: Vec<_> = config
+                .target
+                .addresses
+                .iter()
+                .copied()
+                .map(SocketAddress::from)
+                .collect();
+            let parameters: Vec<_> = config
+                .parameters
+                .iter()
+                .map(|(name, value)| Tuple2(name.as_str().into(), value.as_str().into()))
+                .collect();
+
+            on_test_start(&TestInfo {
+                test,
+                addresses: RSlice::from_slice(&addresses),
+                host: config.target.host.as_deref().map(RStr::from).into(),
+                parameters: RSlice::from_slice(&parameters),
+            });
+        }
+
+        Ok((plugin, test))
+    }
+    /// called once no more connections will be started
+    fn end_plugin(plugin: &ConnectionPlugin_Ref, test: u64) {
+        if let Some(on_test_end) = plugin.on_test_end() {
+            on_test_end(test);
+        }
+    }
+    /// `scheduled` is the intended start of the request in the open-loop mode
     fn spawn_connection(
         plugin: &ConnectionPlugin_Ref,
-        ip: SocketAddr,
+        config: &TestConfig,
+        connections: &mut Connections,
         tx: UnboundedSender<Message>,
+        scheduled: Option<Instant>,
     ) {
-        let connection_timer = ConnectionTimer::new(ip, tx);
+        let request_start = RequestStart::default();
+        let connection_timer = ConnectionTimer::new(
+            connections,
+            config.target.next_address(),
+            config.target.host.clone(),
+            tx.clone(),
+            request_start.clone(),
+            scheduled,
+            config.parameters.clone(),
+        );
+        let id = connection_timer.id;
         let connection_timer = ConnectionTimerBox::from_value(connection_timer, TD_Opaque);
 
-        let task = plugin.run_connection()(connection_timer);
-        tokio::spawn(task);
+        let task = Gated {
+            task: plugin.run_connection()(connection_timer),
+            gate: connections.gate.clone(),
+            request_start: request_start.clone(),
+        };
+        let timeouts = config.timeouts;
+
+        if timeouts.request.is_none() && timeouts.connection.is_none() {
+            connections.tasks.spawn(task);
+            return;
+        }
+
+        let gate = connections.gate.clone();
+        connections.tasks.spawn(async move {
+            tokio::select! {
+                _ = task => {}
+                message = watchdog(timeouts, request_start.clone(), gate) => {
+                    let started = request_start.lock().unwrap().unwrap_or_else(Instant::now);
+
+                    // dropping the plugin future cancels it, ending the connection
+                    let failure = Failure {
+                        kind: ErrorKind::Timeout,
+                        message,
+                        // the metrics are dropped together with the timer
+                        metrics: Vec::new(),
+                        warmup: false,
+                        connection: id,
+                        started: wall_clock(started),
+                    };
+                    let _ = tx.send(Message::Failed(failure));
+                }
+            }
+        });
+    }
+}
+
+/// wall clock time of the instant in the past
+fn wall_clock(at: Instant) -> SystemTime {
+    let now = SystemTime::now();
+
+    now.checked_sub(at.elapsed()).unwrap_or(now)
+}
+
+/// resolves with the error message once the request or the connection runs out of time
+async fn watchdog(timeouts: Timeouts, request_start: RequestStart, gate: Arc<PauseGate>) -> String {
+    let spawned = Instant::now();
+    let paused_before = gate.paused_time();
+
+    loop {
+        let request_deadline = timeouts
+            .request
+            .and_then(|timeout| request_start.lock().unwrap().map(|start| start + timeout));
+
+        // the connection doesn't age while the test is paused
+        let now = Instant::now();
+        let paused = gate.paused_time().saturating_sub(paused_before);
+        let age = (now - spawned).saturating_sub(paused);
+        let connection_deadline = timeouts
+            .connection
+            .map(|timeout| now + timeout.saturating_sub(age));
+
+        if let Some(deadline) = connection_deadline
+            && deadline <= now
+        {
+            return format!(
+                "connection timed out after {:?}",
+                timeouts.connection.unwrap()
+            );
+        }
+        if let Some(deadline) = request_deadline
+            && deadline <= now
+        {
+            return format!("request timed out after {:?}", timeouts.request.unwrap());
+        }
+
+        // when no request is in progress check again after the request timeout,
+        // a request started in the meantime can't expire before that
+        let next_check = [
+            connection_deadline,
+            request_deadline.or(timeouts.request.map(|timeout| now + timeout)),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .expect("at least one timeout is set");
+
+        tokio::time::sleep_until(next_check.into()).await;
     }
 }
 
@@ -41,19 +637,17 @@ impl ConnectionManager {
 impl ConnectionManager {
     pub async fn run_test_increase(
         max_connections: u64,
-        ip: SocketAddr,
-        plugins_path: String,
-        plugin_name: String,
-    ) -> Arc<(Wrapper<(u64, u64)>, UnboundedSender<Message>)> {
-        let plugin = load_root_module_from_file(std::path::Path::new(&format!(
-            "{plugins_path}/{plugin_name}"
-        )))
-        .expect("This plugin is not compatible");
+        config: TestConfig,
+    ) -> Result<TestHandle<(Outcome, u64)>, LoadError> {
+        let (plugin, test) = ConnectionManager::start_plugin(&config)?;
 
         let (tx_wrapper, rx_wrapper) = unbounded_channel();
         let (tx_connections, mut rx_connections) = unbounded_channel();
 
         let mut paused = false;
+        let mut connections = Connections::new(test);
+        // the mode ends on its own
+        let mut progress = Progress::new(config.warmup, Limits::default(), config.timeouts.drain);
 
         let mut current_connections_cap = 1;
         let mut current_connections = 1;
@@ -62,24 +656,57 @@ impl ConnectionManager {
         const REQUESTS_THRESHOLD: u64 = 10;
 
         //starting point
-        let ip = ip;
         let tx = tx_connections.clone();
-        ConnectionManager::spawn_connection(&plugin, ip, tx.clone());
+        ConnectionManager::spawn_connection(&plugin, &config, &mut connections, tx.clone(), None);
 
         tokio::spawn(async move {
             while let Some(message) = rx_connections.recv().await {
                 use Message::*;
 
+                connections.reap();
+
                 match message {
-                    Data(latency) if current_connections_cap <= max_connections => {
-                        requests += 1;
-                        tx_wrapper.send((latency, current_connections_cap)).unwrap();
+                    // the receiver is dropped when the test is stopped before
+                    // it started, the connections are still stopped below
+                    Data(mut sample) if current_connections_cap <= max_connections => {
+                        sample.warmup = progress.tag();
+                        // the first step starts once the warm-up is over
+                        if !sample.warmup {
+                            requests += 1;
+                        }
+                        if tx_wrapper
+                            .send((Outcome::Success(sample), current_connections_cap))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    // failed requests count towards the threshold too
+                    Failed(mut failure) if current_connections_cap <= max_connections => {
+                        failure.warmup = progress.tag();
+                        if !failure.warmup {
+                            requests += 1;
+                        }
+                        if tx_wrapper
+                            .send((Outcome::Failure(failure), current_connections_cap))
+                            .is_err()
+                        {
+                            break;
+                        }
                     }
                     ConnectionEnded => {
                         current_connections -= 1;
                     }
-                    Pause => paused = true,
-                    Resume => paused = false,
+                    Pause => {
+                        paused = true;
+                        progress.pause();
+                        connections.pause();
+                    }
+                    Resume => {
+                        paused = false;
+                        progress.resume();
+                        connections.resume();
+                    }
                     Stop => break,
                     _ => {}
                 }
@@ -93,24 +720,30 @@ impl ConnectionManager {
                 }
 
                 if current_connections_cap > max_connections {
-                    tx_wrapper
-                        .send((u64::MAX, current_connections_cap))
-                        .unwrap();
+                    let _ = tx_wrapper.send((Outcome::FINISHED, current_connections_cap));
                     break;
                 }
 
                 if !paused && current_connections < current_connections_cap {
                     for _ in 0..(current_connections_cap - current_connections) {
-                        let ip = ip;
                         let tx = tx_connections.clone();
-                        ConnectionManager::spawn_connection(&plugin, ip, tx);
+                        ConnectionManager::spawn_connection(
+                            &plugin,
+                            &config,
+                            &mut connections,
+                            tx,
+                            None,
+                        );
                         current_connections += 1;
                     }
                 }
             }
+
+            connections.stop(progress.drain_left()).await;
+            ConnectionManager::end_plugin(&plugin, test);
         });
 
-        Arc::new((Wrapper(rx_wrapper), tx))
+        Ok(Arc::new((Wrapper(rx_wrapper), tx)))
     }
 }
 
@@ -118,14 +751,9 @@ impl ConnectionManager {
 impl ConnectionManager {
     pub async fn run_test_constant(
         connections_number: u64,
-        ip: SocketAddr,
-        plugins_path: String,
-        plugin_name: String,
-    ) -> Arc<(Wrapper<u64>, UnboundedSender<Message>)> {
-        let plugin = load_root_module_from_file(std::path::Path::new(&format!(
-            "{plugins_path}/{plugin_name}"
-        )))
-        .expect("This plugin is not compatible");
+        config: TestConfig,
+    ) -> Result<TestHandle<Outcome>, LoadError> {
+        let (plugin, test) = ConnectionManager::start_plugin(&config)?;
 
         let (tx_wrapper, rx_wrapper) = unbounded_channel();
         let (tx_connections, mut rx_connections) = unbounded_channel();
@@ -134,42 +762,290 @@ impl ConnectionManager {
         let tx = tx_connections.clone();
 
         let mut paused = false;
+        let mut connections = Connections::new(test);
+        let mut progress = Progress::new(config.warmup, config.limits, config.timeouts.drain);
         let mut current_connections = connections_number;
 
         for _ in 0..connections_number {
-            let ip = ip;
             let tx = tx_connections.clone();
-            ConnectionManager::spawn_connection(&plugin, ip, tx);
+            ConnectionManager::spawn_connection(&plugin, &config, &mut connections, tx, None);
         }
 
         tokio::spawn(async move {
-            while let Some(message) = rx_connections.recv().await {
-                use Message::*;
+            loop {
+                connections.reap();
 
-                match message {
-                    Data(latency) => {
-                        tx_wrapper.send(latency).unwrap();
+                tokio::select! {
+                    message = rx_connections.recv() => {
+                        use Message::*;
+
+                        let Some(message) = message else { break };
+                        match message {
+                            // requests finished after the limit aren't counted
+                            Data(_) | Failed(_) if progress.draining() => {}
+                            Data(mut sample) => {
+                                sample.warmup = progress.tag();
+                                if tx_wrapper.send(Outcome::Success(sample)).is_err() {
+                                    break;
+                                }
+                            }
+                            Failed(mut failure) => {
+                                failure.warmup = progress.tag();
+                                if tx_wrapper.send(Outcome::Failure(failure)).is_err() {
+                                    break;
+                                }
+                            }
+                            ConnectionEnded => {
+                                current_connections -= 1;
+                            }
+                            Pause => {
+                                paused = true;
+                                progress.pause();
+                                connections.pause();
+                            }
+                            Resume => {
+                                paused = false;
+                                progress.resume();
+                                connections.resume();
+                            }
+                            Stop => break,
+                        }
                     }
-                    ConnectionEnded => {
-                        current_connections -= 1;
+                    _ = sleep_until(progress.deadline()) => {}
+                }
+
+                // no new connections once a limit is reached
+                if progress.check_limits() {
+                    if progress.drained(current_connections) {
+                        let _ = tx_wrapper.send(Outcome::FINISHED);
+                        break;
                     }
-                    Pause => paused = true,
-                    Resume => paused = false,
-                    Stop => break,
+                    continue;
                 }
 
                 if !paused && current_connections < connections_number {
                     for _ in 0..(connections_number - current_connections) {
-                        let ip = ip;
                         let tx = tx_connections.clone();
-                        ConnectionManager::spawn_connection(&plugin, ip, tx);
+                        ConnectionManager::spawn_connection(
+                            &plugin,
+                            &config,
+                            &mut connections,
+                            tx,
+                            None,
+                        );
                         current_connections += 1;
                     }
                 }
             }
+
+            connections.stop(progress.drain_left()).await;
+            ConnectionManager::end_plugin(&plugin, test);
+        });
+
+        Ok(Arc::new((Wrapper(rx_wrapper), tx)))
+    }
+}
+
+// load profile mode
+impl ConnectionManager {
+    /// how often the number of connections is adjusted to the profile
+    const PROFILE_STEP: Duration = Duration::from_millis(100);
+
+    /// follows the profile stages, the current number of connections is sent
+    /// together with every result
+    pub async fn run_test_profile(
+        profile: Profile,
+        config: TestConfig,
+    ) -> Result<TestHandle<(Outcome, u64)>, LoadError> {
+        let (plugin, test) = ConnectionManager::start_plugin(&config)?;
+
+        let (tx_wrapper, rx_wrapper) = unbounded_channel();
+        let (tx_connections, mut rx_connections) = unbounded_channel();
+
+        // for return purposed
+        let tx = tx_connections.clone();
+
+        let mut paused = false;
+        let mut connections = Connections::new(test);
+        // the mode ends with the profile
+        let mut progress = Progress::new(config.warmup, Limits::default(), config.timeouts.drain);
+        let mut current_connections = 0;
+        let mut target_connections = 0;
+
+        // time spent in the profile, doesn't advance while paused
+        let mut elapsed = Duration::ZERO;
+        let mut last_step = Instant::now();
+        let mut steps = tokio::time::interval(ConnectionManager::PROFILE_STEP);
+
+        tokio::spawn(async move {
+            loop {
+                connections.reap();
+
+                tokio::select! {
+                    _ = steps.tick() => {
+                        let now = Instant::now();
+                        if !paused {
+                            elapsed += now - last_step;
+                        }
+                        last_step = now;
+
+                        let Some(connections) = profile.connections_at(elapsed) else {
+                            let _ = tx_wrapper.send((Outcome::FINISHED, target_connections));
+                            break;
+                        };
+                        target_connections = connections;
+                    }
+                    message = rx_connections.recv() => {
+                        use Message::*;
+
+                        let Some(message) = message else { break };
+                        match message {
+                            Data(mut sample) => {
+                                sample.warmup = progress.tag();
+                                if tx_wrapper.send((Outcome::Success(sample), target_connections)).is_err() {
+                                    break;
+                                }
+                            }
+                            Failed(mut failure) => {
+                                failure.warmup = progress.tag();
+                                if tx_wrapper.send((Outcome::Failure(failure), target_connections)).is_err() {
+                                    break;
+                                }
+                            }
+                            // connections above the target aren't replaced when they end
+                            ConnectionEnded => current_connections -= 1,
+                            Pause => {
+                                paused = true;
+                                progress.pause();
+                                connections.pause();
+                            }
+                            Resume => {
+                                paused = false;
+                                progress.resume();
+                                connections.resume();
+                            }
+                            Stop => break,
+                        }
+                    }
+                }
+
+                if !paused && current_connections < target_connections {
+                    for _ in 0..(target_connections - current_connections) {
+                        let tx = tx_connections.clone();
+                        ConnectionManager::spawn_connection(
+                            &plugin,
+                            &config,
+                            &mut connections,
+                            tx,
+                            None,
+                        );
+                        current_connections += 1;
+                    }
+                }
+            }
+
+            connections.stop(progress.drain_left()).await;
+            ConnectionManager::end_plugin(&plugin, test);
+        });
+
+        Ok(Arc::new((Wrapper(rx_wrapper), tx)))
+    }
+}
+
+// open-loop mode
+impl ConnectionManager {
+    /// starts `rate` requests per second regardless of how many are still
+    /// in progress, the number in progress is sent together with every result
+    pub async fn run_test_rate(
+        rate: u64,
+        config: TestConfig,
+    ) -> Result<TestHandle<(Outcome, u64)>, LoadError> {
+        let (plugin, test) = ConnectionManager::start_plugin(&config)?;
+
+        let (tx_wrapper, rx_wrapper) = unbounded_channel();
+        let (tx_connections, mut rx_connections) = unbounded_channel();
+
+        // for return purposed
+        let tx = tx_connections.clone();
+
+        let mut paused = false;
+        let mut connections = Connections::new(test);
+        let mut progress = Progress::new(config.warmup, config.limits, config.timeouts.drain);
+        let mut in_flight = 0;
+
+        // a zero rate is run as one per second, rates above a billion per second
+        // would round the period down to zero, which the interval rejects
+        let period = Duration::from_secs_f64(1.0 / rate.max(1) as f64).max(Duration::from_nanos(1));
+        // missed ticks fire immediately, keeping the schedule when the host falls behind
+        let mut schedule = tokio::time::interval(period);
+        schedule.set_missed_tick_behavior(MissedTickBehavior::Burst);
+
+        tokio::spawn(async move {
+            loop {
+                connections.reap();
+
+                tokio::select! {
+                    scheduled = schedule.tick(), if !paused && !progress.draining() => {
+                        let tx = tx_connections.clone();
+                        let scheduled = Some(scheduled.into_std());
+                        ConnectionManager::spawn_connection(
+                            &plugin,
+                            &config,
+                            &mut connections,
+                            tx,
+                            scheduled,
+                        );
+                        in_flight += 1;
+                    }
+                    message = rx_connections.recv() => {
+                        use Message::*;
+
+                        let Some(message) = message else { break };
+                        match message {
+                            // requests finished after the limit aren't counted
+                            Data(_) | Failed(_) if progress.draining() => {}
+                            Data(mut sample) => {
+                                sample.warmup = progress.tag();
+                                if tx_wrapper.send((Outcome::Success(sample), in_flight)).is_err() {
+                                    break;
+                                }
+                            }
+                            Failed(mut failure) => {
+                                failure.warmup = progress.tag();
+                                if tx_wrapper.send((Outcome::Failure(failure), in_flight)).is_err() {
+                                    break;
+                                }
+                            }
+                            ConnectionEnded => in_flight -= 1,
+                            Pause => {
+                                paused = true;
+                                progress.pause();
+                                connections.pause();
+                            }
+                            Resume => {
+                                paused = false;
+                                progress.resume();
+                                connections.resume();
+                                // don't make up for the requests skipped during pause
+                                schedule.reset();
+                            }
+                            Stop => break,
+                        }
+                    }
+                    _ = sleep_until(progress.deadline()) => {}
+                }
+
+                if progress.check_limits() && progress.drained(in_flight) {
+                    let _ = tx_wrapper.send((Outcome::FINISHED, in_flight));
+                    break;
+                }
+            }
+
+            connections.stop(progress.drain_left()).await;
+            ConnectionManager::end_plugin(&plugin, test);
         });
 
-        Arc::new((Wrapper(rx_wrapper), tx))
+        Ok(Arc::new((Wrapper(rx_wrapper), tx)))
     }
 }
 
@@ -193,19 +1069,61 @@ impl<T> Stream for Wrapper<T> {
 }
 
 struct ConnectionTimer {
+    id: u64,
     ip: SocketAddr,
+    host: Option<Arc<str>>,
     start: Instant,
     tx: UnboundedSender<Message>,
+    request_start: RequestStart,
+    /// used instead of the first `start` call so the latency includes the
+    /// time the request waited for its turn
+    scheduled: Option<Instant>,
+    /// marks of the request in progress
+    marks: Vec<(String, Instant)>,
+    /// metrics of the request in progress
+    metrics: Vec<(String, Metric)>,
+    gate: Arc<PauseGate>,
+    test: u64,
+    /// paused time of the test when the connection was spawned
+    paused_before: Duration,
+    parameters: Arc<Parameters>,
 }
 
 impl ConnectionTimer {
-    fn new(ip: SocketAddr, tx: UnboundedSender<Message>) -> Self {
+    /// numbered after the connections spawned before it
+    fn new(
+        connections: &mut Connections,
+        ip: SocketAddr,
+        host: Option<Arc<str>>,
+        tx: UnboundedSender<Message>,
+        request_start: RequestStart,
+        scheduled: Option<Instant>,
+        parameters: Arc<Parameters>,
+    ) -> Self {
+        connections.last_id += 1;
+
         ConnectionTimer {
+            id: connections.last_id,
             ip,
+            host,
             start: Instant::now(),
             tx,
+            request_start,
+            scheduled,
+            marks: Vec::new(),
+            metrics: Vec::new(),
+            gate: connections.gate.clone(),
+            test: connections.test,
+            paused_before: connections.gate.paused_time(),
+            parameters,
         }
     }
+    fn metric(&mut self, name: RStr<'_>) -> Option<&mut Metric> {
+        self.metrics
+            .iter_mut()
+            .find(|(metric, _)| metric == name.as_str())
+            .map(|(_, value)| value)
+    }
 }
 
 impl Drop for ConnectionTimer {
@@ -216,15 +1134,48 @@ impl Drop for ConnectionTimer {
 
 impl interface::ConnectionTimer for ConnectionTimer {
     fn start(&mut self) {
-        self.start = Instant::now();
+        // held by the pause gate before its first request, the wait isn't its latency
+        if self.scheduled.is_some() && self.gate.paused_time() > self.paused_before {
+            self.scheduled = None;
+        }
+
+        self.start = self.scheduled.take().unwrap_or_else(Instant::now);
+        *self.request_start.lock().unwrap() = Some(self.start);
+        self.marks.clear();
+        self.metrics.clear();
     }
     fn stop(&self) {
-        let latency = self.start.elapsed().as_millis();
-        let _ = self.tx.send(Message::Data(latency as u64));
+        *self.request_start.lock().unwrap() = None;
+
+        let now = Instant::now();
+        let mut phases = Vec::new();
+        let mut phase_start = self.start;
+
+        for (name, at) in &self.marks {
+            phases.push((name.clone(), at.saturating_duration_since(phase_start)));
+            phase_start = *at;
+        }
+        if !phases.is_empty() {
+            phases.push((
+                "stop".to_owned(),
+                now.saturating_duration_since(phase_start),
+            ));
+        }
+
+        let sample = Sample {
+            latency: now - self.start,
+            phases,
+            metrics: self.metrics.clone(),
+            // tagged by the test loop
+            warmup: false,
+            connection: self.id,
+            started: wall_clock(self.start),
+        };
+        let _ = self.tx.send(Message::Data(sample));
     }
-    fn ip_v4(&self) -> abi_stable::std_types::Tuple2<[u8; 4], u16> where {
+    fn ip_v4(&self) -> Tuple2<[u8; 4], u16> where {
         let SocketAddr::V4(addr) = self.ip else {
-            panic!("its not ipv4")
+            panic!("target is not an IPv4 address, use `socket_addr`")
         };
 
         let port = addr.port();
@@ -234,7 +1185,7 @@ impl interface::ConnectionTimer for ConnectionTimer {
     }
     fn ip_v6(&self) -> Tuple2<[u8; 16], u16> where {
         let SocketAddr::V6(addr) = self.ip else {
-            panic!("its not ipv4")
+            panic!("target is not an IPv6 address, use `socket_addr`")
         };
 
         let port = addr.port();
@@ -242,4 +1193,122 @@ impl interface::ConnectionTimer for ConnectionTimer {
 
         Tuple2::from_tuple((ip, port))
     }
+    fn fail(&self, kind: ErrorKind, message: RStr<'_>) {
+        *self.request_start.lock().unwrap() = None;
+
+        let failure = Failure {
+            kind,
+            message: message.to_string(),
+            metrics: self.metrics.clone(),
+            warmup: false,
+            connection: self.id,
+            started: wall_clock(self.start),
+        };
+        let _ = self.tx.send(Message::Failed(failure));
+    }
+    fn mark(&mut self, name: RStr<'_>) {
+        self.marks.push((name.to_string(), Instant::now()));
+    }
+    fn parameter(&self, name: RStr<'_>) -> ROption<RStr<'_>> {
+        self.parameters
+            .get(name.as_str())
+            .map(|value| value.as_str().into())
+            .into()
+    }
+    fn count(&mut self, name: RStr<'_>, value: u64) {
+        match self.metric(name) {
+            Some(Metric::Counter(count)) => *count += value,
+            // the kind of the metric is set by its first report
+            Some(Metric::Gauge(_)) => {}
+            None => self
+                .metrics
+                .push((name.to_string(), Metric::Counter(value))),
+        }
+    }
+    fn gauge(&mut self, name: RStr<'_>, value: f64) {
+        match self.metric(name) {
+            Some(Metric::Gauge(gauge)) => *gauge = value,
+            Some(Metric::Counter(_)) => {}
+            None => self.metrics.push((name.to_string(), Metric::Gauge(value))),
+        }
+    }
+    fn host(&self) -> ROption<RStr<'_>> {
+        self.host.as_deref().map(RStr::from).into()
+    }
+    fn socket_addr(&self) -> SocketAddress {
+        self.ip.into()
+    }
+    fn test(&self) -> u64 {
+        self.test
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warmup() {
+        assert_eq!("30s".parse(), Ok(Warmup::Duration(Duration::from_secs(30))));
+        assert_eq!(
+            " 2m ".parse(),
+            Ok(Warmup::Duration(Duration::from_secs(120)))
+        );
+        assert_eq!("500 req".parse(), Ok(Warmup::Requests(500)));
+        assert_eq!("500req".parse(), Ok(Warmup::Requests(500)));
+        assert_eq!("0 req".parse(), Ok(Warmup::Requests(0)));
+
+        assert_eq!(
+            "many req".parse::<Warmup>(),
+            Err("bad requests number `many req`".to_owned())
+        );
+        assert_eq!(
+            "-1 req".parse::<Warmup>(),
+            Err("bad requests number `-1 req`".to_owned())
+        );
+        assert_eq!(
+            "500".parse::<Warmup>(),
+            Err("bad duration `500`".to_owned())
+        );
+
+        for warmup in [
+            Warmup::Duration(Duration::from_millis(1500)),
+            Warmup::Requests(10),
+        ] {
+            assert_eq!(warmup.to_string().parse(), Ok(warmup));
+        }
+    }
+
+    #[test]
+    fn requests_warmup_and_limit() {
+        let limits = Limits {
+            duration: None,
+            requests: Some(2),
+        };
+        let mut progress = Progress::new(Some(Warmup::Requests(3)), limits, None);
+
+        assert_eq!(
+            (0..5).map(|_| progress.tag()).collect::<Vec<_>>(),
+            [true, true, true, false, false]
+        );
+        assert!(progress.check_limits());
+        assert!(progress.drained(1));
+    }
+
+    #[test]
+    fn duration_limit_counts_from_warmup_end() {
+        let limits = Limits {
+            duration: Some(Duration::from_secs(60)),
+            requests: None,
+        };
+        let progress = Progress::new(
+            Some(Warmup::Duration(Duration::from_secs(30))),
+            limits,
+            None,
+        );
+        let deadline = progress.deadline().unwrap();
+
+        assert!(deadline > Instant::now() + Duration::from_secs(89));
+        assert!(deadline <= Instant::now() + Duration::from_secs(90));
+    }
 }
@@ -5,27 +5,30 @@ use iced::widget::column;
 pub struct Buttons {}
 
 impl Buttons {
-    pub fn view(&self, cant_run: bool, test_running: bool, paused: bool) -> Element<Message> {
+    /// `run_disabled` keeps the run button greyed out, `cant_run` says why
+    pub fn view(
+        &self,
+        cant_run: Option<&'static str>,
+        run_disabled: bool,
+        test_running: bool,
+        paused: bool,
+    ) -> Element<'_, Message> {
         macro_rules! new_button {
             ($content:expr, $msg:expr) => {
                 button($content).on_press($msg)
             };
         }
-        let run_error = if cant_run {
-            "Fill all inputs before running!"
-        } else {
-            ""
-        };
+        let run_error = cant_run.unwrap_or_default();
 
         // buttons
         let buttons = row![];
 
-        let run_button = (!test_running).then_some(
-            new_button!("Run", Message::RunTest).style(|theme, status| primary(theme, status)),
-        );
-        let stop_button = test_running.then_some(
-            new_button!("Stop", Message::Stop).style(|theme, status| danger(theme, status)),
-        );
+        let run_button = (!test_running).then(|| {
+            button("Run")
+                .on_press_maybe((!run_disabled).then_some(Message::RunTest))
+                .style(primary)
+        });
+        let stop_button = test_running.then_some(new_button!("Stop", Message::Stop).style(danger));
 
         let resume_pause_button = if test_running && !paused {
             Some(
@@ -1,45 +1,288 @@
 use super::*;
 use crate::Message;
+use crate::benchmark::{self, Metric};
+use crate::export::Export;
+use crate::profile::Profile;
+use crate::stats::{self, Interval, Percentiles, Statistic, TimeUnit};
 use iced::widget::{button, column, container, row, text};
+use plotters::{
+    chart::{ChartContext, DualCoordChartContext},
+    coord::{
+        cartesian::Cartesian2d,
+        types::{RangedCoordf64, RangedCoordu64},
+    },
+    style::{Color as _, RGBAColor, RGBColor},
+};
 use plotters_iced::{Chart, ChartBuilder, ChartWidget, DrawingBackend};
-use std::{collections::VecDeque, default::Default};
+use std::{collections::VecDeque, default::Default, fmt::Display};
 
+/// series drawn on the secondary axis
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SecondarySeries {
+    #[default]
+    Throughput,
+    /// metric reported by the plugin
+    Metric(String),
+}
+
+impl Display for SecondarySeries {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecondarySeries::Throughput => write!(f, "throughput"),
+            SecondarySeries::Metric(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// results of one target of the test
 #[derive(Clone, Debug)]
-pub struct ConnectionChart {
+pub struct Series {
+    /// address and plugin of the target, shown when targets are compared
+    pub label: String,
     pub index: u64,
-    pub data_points: VecDeque<(u64, u64)>,
+    pub data_points: VecDeque<(u64, Interval)>,
+    /// requests and errors since the start of the test without the warm-up
+    pub total: Interval,
+    /// requests in progress in the open-loop mode
+    pub in_flight: Option<u64>,
+    /// connections currently required by the profile
+    pub connections: Option<u64>,
+}
+
+impl Series {
+    pub fn new(label: String) -> Self {
+        Series {
+            label,
+            index: 1,
+            data_points: VecDeque::new(),
+            total: Interval::default(),
+            in_flight: None,
+            connections: None,
+        }
+    }
+    /// `scroll` drops the oldest point once the chart is full
+    fn push(&mut self, data: Interval, scroll: bool) {
+        if scroll && self.index > ConnectionChart::X_AXIS {
+            self.data_points.pop_front();
+        }
+
+        self.data_points.push_back((self.index, data));
+        self.index += 1;
+    }
+}
+
+/// colours of the saved chart
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChartTheme {
+    /// same as in the window
+    #[default]
+    Dark,
+    /// white background for printed documents
+    Light,
+}
+
+impl ChartTheme {
+    pub fn background(self) -> RGBColor {
+        match self {
+            ChartTheme::Dark => RGBColor(32, 34, 37),
+            ChartTheme::Light => RGBColor(255, 255, 255),
+        }
+    }
+    /// text, grid and marks
+    fn foreground(self) -> RGBColor {
+        match self {
+            ChartTheme::Dark => RGBColor(255, 255, 255),
+            ChartTheme::Light => RGBColor(0, 0, 0),
+        }
+    }
+    /// saved tests, drawn dimmed and unlike the colours of the targets
+    fn baseline(self, index: usize) -> RGBAColor {
+        let (colors, opacity) = match self {
+            ChartTheme::Dark => ([(200, 200, 200), (255, 170, 110), (130, 230, 230)], 0.4),
+            ChartTheme::Light => ([(90, 90, 90), (200, 100, 30), (0, 130, 130)], 0.5),
+        };
+        let (r, g, b) = colors[index % colors.len()];
+
+        RGBColor(r, g, b).mix(opacity)
+    }
+}
+
+/// size and look of the saved chart
+#[derive(Clone, Copy, Debug)]
+pub struct ImageOptions {
+    /// in pixels
+    pub width: u32,
+    pub height: u32,
+    /// scales the text and lines, the default keeps their size in pixels
+    pub dpi: u32,
+    pub theme: ChartTheme,
+}
+
+impl ImageOptions {
+    pub const DEFAULT_DPI: u32 = 96;
+}
+
+/// colours and line widths of the drawn chart
+#[derive(Clone, Copy, Debug)]
+struct Style {
+    theme: ChartTheme,
+    /// multiplies the sizes given for the default dpi
+    scale: f64,
+}
+
+impl Style {
+    const SCREEN: Style = Style {
+        theme: ChartTheme::Dark,
+        scale: 1.0,
+    };
+
+    fn size(&self, size: u32) -> u32 {
+        ((size as f64 * self.scale).round() as u32).max(1)
+    }
+}
+
+/// target of a saved test drawn dimmed behind the current one
+#[derive(Clone, Debug)]
+pub struct Baseline {
+    pub label: String,
+    /// the x-axis is the connection number instead of time
+    pub increase: bool,
+    pub points: Vec<(u64, Percentiles)>,
+}
+
+impl Baseline {
+    /// every target of the saved test, `name` tells the files apart
+    pub fn from_export(name: &str, export: Export) -> Vec<Baseline> {
+        export
+            .targets
+            .into_iter()
+            .map(|target| Baseline {
+                label: format!("{name} {}", target.label),
+                increase: export.mode == benchmark::Mode::Increase,
+                points: target
+                    .intervals
+                    .iter()
+                    .map(|interval| (interval.index, interval.percentiles()))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConnectionChart {
+    /// target set in the inputs, its phases and metrics are charted
+    pub main: Series,
+    /// other targets of the A/B test drawn over the main one
+    pub compared: Vec<Series>,
     pub max_connections: Option<u64>,
+    pub statistic: Statistic,
+    /// stages of the profile mode, their boundaries are marked on the chart
+    pub profile: Option<Profile>,
+    /// phases marked by the plugin, in the order they were first seen
+    pub phase_names: Vec<String>,
+    pub hidden_phases: Vec<String>,
+    /// draw phases on top of each other instead of separate lines
+    pub stack_phases: bool,
+    /// metrics reported by the plugin, in the order they were first seen
+    pub metric_names: Vec<String>,
+    pub secondary: SecondarySeries,
+    /// the test ended on its own, the totals are shown as its summary
+    pub finished: bool,
+    /// loaded from files, kept between the tests
+    pub baselines: Vec<Baseline>,
+    /// p25-p75 and p10-p90 shaded around the latency of the main target
+    pub show_bands: bool,
+    pub show_p99: bool,
 }
 
 impl Default for ConnectionChart {
     fn default() -> Self {
         ConnectionChart {
-            index: 1,
-            data_points: VecDeque::new(),
+            main: Series::new(String::new()),
+            compared: Vec::new(),
             max_connections: None,
+            statistic: Statistic::default(),
+            profile: None,
+            phase_names: Vec::new(),
+            hidden_phases: Vec::new(),
+            stack_phases: false,
+            metric_names: Vec::new(),
+            secondary: SecondarySeries::default(),
+            finished: false,
+            baselines: Vec::new(),
+            show_bands: true,
+            show_p99: true,
         }
     }
 }
 
 impl ConnectionChart {
     const X_AXIS: u64 = 60;
+    // 50ms, in nanoseconds
+    const DEFAULT_Y_AXIS: u64 = 50_000_000;
+    // requests per second
+    const DEFAULT_THROUGHPUT_AXIS: f64 = 100.0;
+    // targets of the A/B test, the first one is the latency colour
+    const SERIES_COLORS: [(u8, u8, u8); 5] = [
+        (0, 175, 255),
+        (255, 120, 200),
+        (120, 220, 120),
+        (255, 210, 80),
+        (200, 150, 255),
+    ];
+
+    /// starts a new test of the targets, the first one is the main target
+    pub fn clear(&mut self, labels: Vec<String>) {
+        let mut series = labels.into_iter().map(Series::new);
 
-    pub fn clear(&mut self) {
-        self.index = 1;
-        self.data_points.clear();
+        self.main = series.next().unwrap_or_else(|| Series::new(String::new()));
+        self.compared = series.collect();
+        self.phase_names.clear();
+        self.metric_names.clear();
+        self.finished = false;
     }
 
     pub fn update(&mut self, message: Message) {
         use Message::*;
 
         match message {
-            NewChartData(data) => {
-                if self.max_connections.is_none() && self.index > ConnectionChart::X_AXIS {
-                    self.data_points.pop_front();
+            NewChartData(target, data) => {
+                // the whole profile is shown at once
+                let scroll = self.max_connections.is_none() && self.profile.is_none();
+
+                if target > 0 {
+                    if let Some(series) = self.compared.get_mut(target - 1) {
+                        series.push(data, scroll);
+                    }
+                    return;
+                }
+
+                for (name, _) in &data.phases {
+                    if !self.phase_names.contains(name) {
+                        self.phase_names.push(name.clone());
+                    }
+                }
+                for (name, _) in &data.metrics {
+                    if !self.metric_names.contains(name) {
+                        self.metric_names.push(name.clone());
+                    }
                 }
 
-                self.data_points.push_back((self.index, data));
-                self.index += 1;
+                self.main.push(data, scroll);
+            }
+            StatisticChanged(statistic) => self.statistic = statistic,
+            StackPhasesChanged(stack) => self.stack_phases = stack,
+            SecondarySeriesChanged(series) => self.secondary = series,
+            BandsToggled(shown) => self.show_bands = shown,
+            P99LineToggled(shown) => self.show_p99 = shown,
+            BaselinesLoaded(Ok(baselines)) => self.baselines.extend(baselines),
+            ClearBaselines => self.baselines.clear(),
+            PhaseToggled(name, shown) => {
+                self.hidden_phases.retain(|phase| *phase != name);
+                if !shown {
+                    self.hidden_phases.push(name);
+                }
             }
             _ => {}
         }
@@ -50,45 +293,319 @@ impl ConnectionChart {
                 row![
                     container(text!("Server Latency Test")).padding(Padding::default().top(4)),
                     container(text!("-")).padding(Padding::default().top(4)),
-                    button("Save Chart").on_press(Message::Save)
+                    pick_list(
+                        Statistic::ALL,
+                        Some(self.statistic),
+                        Message::StatisticChanged
+                    ),
+                    container(checkbox("Bands", self.show_bands).on_toggle(Message::BandsToggled))
+                        .padding(Padding::default().top(6)),
+                    container(checkbox("p99", self.show_p99).on_toggle(Message::P99LineToggled))
+                        .padding(Padding::default().top(6)),
+                    button("Save Chart").on_press(Message::Save),
+                    button("Load Baseline").on_press(Message::OpenBaseline),
                 ]
+                .push_maybe(
+                    (!self.baselines.is_empty())
+                        .then(|| button("Clear Baselines").on_press(Message::ClearBaselines)),
+                )
+                .push_maybe((!self.metric_names.is_empty()).then(|| self.secondary_view()))
                 .spacing(10)
             )
             .width(Length::Fill)
             .align_x(Alignment::Center),
-            ChartWidget::new(self),
         ]
+        .extend(self.series().map(|series| {
+            container(self.errors_view(series))
+                .width(Length::Fill)
+                .align_x(Alignment::Center)
+                .into()
+        }))
+        .extend(self.series().filter(|_| self.finished).map(|series| {
+            container(self.summary_view(series))
+                .width(Length::Fill)
+                .align_x(Alignment::Center)
+                .into()
+        }))
+        .push_maybe((!self.phase_names.is_empty()).then(|| {
+            container(self.phases_view())
+                .width(Length::Fill)
+                .align_x(Alignment::Center)
+        }))
+        .push(ChartWidget::new(self))
+        .into()
+    }
+    /// lets the user chart a plugin metric instead of the throughput
+    fn secondary_view(&self) -> Element<'_, Message> {
+        let options: Vec<_> = std::iter::once(SecondarySeries::Throughput)
+            .chain(
+                self.metric_names
+                    .iter()
+                    .cloned()
+                    .map(SecondarySeries::Metric),
+            )
+            .collect();
+
+        row![
+            container(text!("Right axis:")).padding(Padding::default().top(4)),
+            pick_list(
+                options,
+                Some(self.secondary.clone()),
+                Message::SecondarySeriesChanged
+            ),
+        ]
+        .spacing(10)
+        .into()
+    }
+    /// name of the target in front of its rows in the A/B test
+    fn label_view<'a>(&self, series: &'a Series) -> Option<Element<'a, Message>> {
+        let color = ConnectionChart::series_color(self.index_of(series));
+
+        (!self.compared.is_empty()).then(|| text(&series.label).color(color).into())
+    }
+    fn summary_view<'a>(&self, series: &'a Series) -> Element<'a, Message> {
+        let summary = &series.total;
+        let percentiles = &summary.percentiles;
+
+        let finished = text!(
+            "Finished: {} requests in {:.1}s, {:.0} req/s, p50 {}, p90 {}, p99 {}, max {}, errors {} ({:.2}%)",
+            summary.requests,
+            summary.duration.as_secs_f64(),
+            summary.throughput(),
+            stats::format_latency(percentiles.p50),
+            stats::format_latency(percentiles.p90),
+            stats::format_latency(percentiles.p99),
+            stats::format_latency(percentiles.max),
+            summary.errors.total(),
+            summary.error_rate()
+        )
+        .color(Color::from_rgb(0.3, 0.9, 0.4));
+
+        row![]
+            .push_maybe(self.label_view(series))
+            .push(finished)
+            .spacing(20)
+            .into()
+    }
+    fn phases_view(&self) -> Element<'_, Message> {
+        let phases = self.phase_names.iter().map(|name| {
+            let name = name.clone();
+            let shown = !self.hidden_phases.contains(&name);
+
+            checkbox(name.clone(), shown)
+                .on_toggle(move |shown| Message::PhaseToggled(name.clone(), shown))
+                .into()
+        });
+
+        row![
+            text!("Phases:"),
+            checkbox("Stack", self.stack_phases).on_toggle(Message::StackPhasesChanged)
+        ]
+        .extend(phases)
+        .spacing(15)
         .into()
     }
+    fn errors_view<'a>(&'a self, series: &'a Series) -> Element<'a, Message> {
+        let last_rate = series
+            .data_points
+            .back()
+            .map_or(0.0, |(_idx, interval)| interval.error_rate());
+        let last_throughput = series
+            .data_points
+            .back()
+            .map_or(0.0, |(_idx, interval)| interval.throughput());
+
+        let warming_up = series
+            .data_points
+            .back()
+            .is_some_and(|(_idx, interval)| interval.warmup);
+
+        let color = if series.total.errors.total() > 0 {
+            Color::from_rgb(1.0, 0.3, 0.3)
+        } else {
+            Color::from_rgb(0.6, 0.6, 0.6)
+        };
+
+        row![]
+            .push_maybe(self.label_view(series))
+            .push_maybe(
+                warming_up.then(|| text!("Warming up").color(Color::from_rgb(0.6, 0.6, 0.6))),
+            )
+            .extend([
+                text!(
+                    "Errors: {} of {} requests ({:.2}%), last point: {:.2}%",
+                    series.total.errors.total(),
+                    series.total.requests,
+                    series.total.error_rate(),
+                    last_rate
+                )
+                .color(color)
+                .into(),
+                text!("{}", series.total.errors)
+                    .color(Color::from_rgb(0.6, 0.6, 0.6))
+                    .into(),
+                text!(
+                    "Throughput: {last_throughput:.0} req/s (average {:.0} req/s)",
+                    series.total.throughput()
+                )
+                .into(),
+            ])
+            .push_maybe(
+                series
+                    .in_flight
+                    .map(|in_flight| text!("In flight: {in_flight}")),
+            )
+            .push_maybe(
+                series
+                    .connections
+                    .map(|connections| text!("Connections: {connections}")),
+            )
+            .push_maybe(self.selected_metric().map(|name| {
+                let last = series
+                    .data_points
+                    .back()
+                    .and_then(|(_idx, interval)| interval.metric(name))
+                    .unwrap_or(0.0);
+
+                text!("{}: {last:.2}", self.secondary_label())
+            }))
+            .spacing(20)
+            .into()
+    }
 }
 
 impl ConnectionChart {
+    /// target in the order of the test, the main one is first
+    pub fn series_mut(&mut self, index: usize) -> Option<&mut Series> {
+        match index {
+            0 => Some(&mut self.main),
+            _ => self.compared.get_mut(index - 1),
+        }
+    }
+    /// main target followed by the compared ones
+    fn series(&self) -> impl Iterator<Item = &Series> {
+        std::iter::once(&self.main).chain(&self.compared)
+    }
+    fn index_of(&self, series: &Series) -> usize {
+        self.series()
+            .position(|other| std::ptr::eq(other, series))
+            .unwrap_or(0)
+    }
+    /// colour of the target, the main one keeps the colour of the latency
+    fn series_color(index: usize) -> Color {
+        let RGBColor(r, g, b) = ConnectionChart::plot_color(index);
+
+        Color::from_rgb8(r, g, b)
+    }
+    fn plot_color(index: usize) -> RGBColor {
+        let colors = ConnectionChart::SERIES_COLORS;
+        let (r, g, b) = colors[index % colors.len()];
+
+        RGBColor(r, g, b)
+    }
+    /// last point of the longest series
+    fn back(&self) -> Option<u64> {
+        self.series()
+            .filter_map(|series| series.data_points.back().map(|point| point.0))
+            .max()
+    }
+    /// baselines with the same x-axis as the chart
+    fn shown_baselines(&self) -> impl Iterator<Item = (usize, &Baseline)> {
+        self.baselines
+            .iter()
+            .enumerate()
+            .filter(|(_, baseline)| baseline.increase == self.max_connections.is_some())
+    }
+    /// selected statistic of the baseline in the visible part of the chart
+    fn baseline_points<'a>(
+        &'a self,
+        baseline: &'a Baseline,
+    ) -> impl Iterator<Item = (u64, u64)> + 'a {
+        let x_range = self.x_range();
+
+        baseline
+            .points
+            .iter()
+            .filter(move |(idx, _)| (x_range.start..=x_range.end).contains(idx))
+            .map(|(idx, percentiles)| (*idx, self.statistic.of(percentiles)))
+    }
     fn x_range(&self) -> std::ops::Range<u64> {
         if let Some(val) = self.max_connections {
             return 1..val;
         }
+        if let Some(profile) = &self.profile {
+            let back = self.back().unwrap_or(0);
 
-        let front = self.data_points.front().unwrap_or(&(1, 0));
-        let back = self
-            .data_points
-            .back()
-            .unwrap_or(&(ConnectionChart::X_AXIS, 0));
+            return 0..std::cmp::max(back, profile.duration().as_secs());
+        }
+
+        let front = self
+            .series()
+            .filter_map(|series| series.data_points.front().map(|point| point.0))
+            .min()
+            .unwrap_or(1);
+        let back = self.back().unwrap_or(ConnectionChart::X_AXIS);
 
-        front.0..std::cmp::max(back.0, ConnectionChart::X_AXIS)
+        front..std::cmp::max(back, ConnectionChart::X_AXIS)
     }
     fn y_range(&self) -> std::ops::Range<u64> {
         let max = self
-            .data_points
-            .iter()
-            .map(|(_idx, val)| *val)
+            .series()
+            .flat_map(|series| &series.data_points)
+            .map(|(_idx, val)| self.statistic.of(&val.percentiles))
+            .chain(
+                self.shown_baselines()
+                    .flat_map(|(_, baseline)| self.baseline_points(baseline))
+                    .map(|x| x.1),
+            )
+            .chain(self.main.data_points.iter().map(|(_idx, val)| {
+                let band = if self.show_bands {
+                    val.percentiles.p90
+                } else {
+                    0
+                };
+                let p99 = if self.show_p99 {
+                    val.percentiles.p99
+                } else {
+                    0
+                };
+
+                std::cmp::max(band, p99)
+            }))
+            .chain(
+                self.stacked_phases()
+                    .last()
+                    .into_iter()
+                    .flatten()
+                    .map(|x| x.1),
+            )
             .max()
-            .unwrap_or(50u64);
+            .unwrap_or(ConnectionChart::DEFAULT_Y_AXIS);
 
         // make some room on the top of chart
         let max = (max as f64 * 1.3) as u64;
 
         0..max
     }
+    fn secondary_range(&self) -> std::ops::Range<f64> {
+        let max = self
+            .series()
+            .flat_map(|series| &series.data_points)
+            .map(|(_idx, val)| match self.selected_metric() {
+                Some(name) => val.metric(name).unwrap_or(0.0),
+                None => val.throughput(),
+            })
+            .fold(0.0, f64::max);
+
+        let max = if max > 0.0 {
+            max
+        } else {
+            ConnectionChart::DEFAULT_THROUGHPUT_AXIS
+        };
+
+        // make some room on the top of chart
+        0.0..max * 1.3
+    }
 }
 
 impl Chart<Message> for ConnectionChart {
@@ -98,17 +615,18 @@ impl Chart<Message> for ConnectionChart {
     fn build_chart<DB: DrawingBackend>(&self, _state: &Self::State, mut chart: ChartBuilder<DB>) {
         use plotters::prelude::*;
 
-        const PLOT_LINE_COLOR: RGBColor = RGBColor(0, 175, 255);
-
         let x_range = self.x_range();
         let y_range = self.y_range();
+        let unit = TimeUnit::fitting(y_range.end);
 
         let mut chart = chart
             .x_label_area_size(40)
             .y_label_area_size(40)
+            .right_y_label_area_size(60)
             .margin(20)
-            .build_cartesian_2d(x_range, y_range)
-            .expect("failed to build chart");
+            .build_cartesian_2d(x_range.clone(), y_range)
+            .expect("failed to build chart")
+            .set_secondary_coord(x_range, self.secondary_range());
 
         chart
             .configure_mesh()
@@ -117,7 +635,7 @@ impl Chart<Message> for ConnectionChart {
             .axis_style(ShapeStyle::from(WHITE.mix(0.45)).stroke_width(1))
             .label_style(TextStyle::from(("sans-serif", 10).into_font()).color(&WHITE))
             .y_labels(10)
-            .y_label_formatter(&|y: &u64| format!("{}ms", y))
+            .y_label_formatter(&|y: &u64| unit.format(*y))
             .x_labels(15)
             .x_label_formatter(&|x: &u64| {
                 if self.max_connections.is_none() {
@@ -129,46 +647,69 @@ impl Chart<Message> for ConnectionChart {
             .draw()
             .expect("failed to draw chart mesh");
 
+        let style = Style::SCREEN;
+
+        self.draw_warmup(&mut chart, &style, 10);
+        self.draw_baselines(&mut chart, &style);
+        self.draw_latency(&mut chart, &style);
+
         chart
-            .draw_series(
-                AreaSeries::new(
-                    self.data_points.iter().map(|x| (x.0, x.1)),
-                    0,
-                    PLOT_LINE_COLOR.mix(0.175),
-                )
-                .border_style(ShapeStyle::from(PLOT_LINE_COLOR).stroke_width(2)),
-            )
-            .expect("failed to draw chart data");
+            .configure_secondary_axes()
+            .axis_desc_style(TextStyle::from(("sans-serif", 10).into_font()).color(&WHITE))
+            .axis_style(ShapeStyle::from(WHITE.mix(0.45)).stroke_width(1))
+            .label_style(TextStyle::from(("sans-serif", 10).into_font()).color(&WHITE))
+            .y_desc(self.secondary_label())
+            .y_labels(10)
+            .y_label_formatter(&|y: &f64| self.format_secondary(*y))
+            .draw()
+            .expect("failed to draw throughput axis");
+
+        self.draw_secondary(&mut chart, &style);
+        self.draw_stage_marks(&mut chart, &style, 10);
+        self.draw_legend(&mut chart, &style, 10);
     }
 }
 
 impl ConnectionChart {
-    // method to build chart in the png file
-    pub fn build_chart_png<DB: DrawingBackend>(&self, mut chart: ChartBuilder<DB>) {
+    // method to build chart in the saved image
+    pub fn build_chart_png<DB: DrawingBackend>(
+        &self,
+        mut chart: ChartBuilder<DB>,
+        options: &ImageOptions,
+    ) {
         use plotters::prelude::*;
 
-        const PLOT_LINE_COLOR: RGBColor = RGBColor(0, 175, 255);
-
         let x_range = self.x_range();
         let y_range = self.y_range();
+        let unit = TimeUnit::fitting(y_range.end);
+        let style = Style {
+            theme: options.theme,
+            scale: options.dpi as f64 / ImageOptions::DEFAULT_DPI as f64,
+        };
+        let size = |size: u32| style.size(size);
+        let foreground = options.theme.foreground();
 
         let mut chart = chart
-            .x_label_area_size(60)
-            .y_label_area_size(100)
-            .margin(20)
-            .build_cartesian_2d(x_range, y_range)
-            .expect("failed to build chart");
+            .x_label_area_size(size(60))
+            .y_label_area_size(size(100))
+            .right_y_label_area_size(size(100))
+            .margin(size(20))
+            .build_cartesian_2d(x_range.clone(), y_range)
+            .expect("failed to build chart")
+            .set_secondary_coord(x_range, self.secondary_range());
 
         chart
             .configure_mesh()
-            .axis_desc_style(TextStyle::from(("sans-serif", 30).into_font()).color(&WHITE))
-            .bold_line_style(WHITE.mix(0.30))
-            .light_line_style(WHITE.mix(0.1))
-            .axis_style(ShapeStyle::from(WHITE.mix(0.65)).stroke_width(1))
-            .label_style(TextStyle::from(("sans-serif", 15).into_font()).color(&WHITE))
-            .y_desc("Latency")
+            .axis_desc_style(
+                TextStyle::from(("sans-serif", size(30)).into_font()).color(&foreground),
+            )
+            .bold_line_style(foreground.mix(0.30))
+            .light_line_style(foreground.mix(0.1))
+            .axis_style(ShapeStyle::from(foreground.mix(0.65)).stroke_width(size(1)))
+            .label_style(TextStyle::from(("sans-serif", size(15)).into_font()).color(&foreground))
+            .y_desc(format!("Latency ({})", self.statistic))
             .y_labels(10)
-            .y_label_formatter(&|y: &u64| format!("{}ms", y))
+            .y_label_formatter(&|y: &u64| unit.format(*y))
             .x_desc(if self.max_connections.is_some() {
                 "Connection Number"
             } else {
@@ -185,15 +726,502 @@ impl ConnectionChart {
             .draw()
             .expect("failed to draw chart mesh");
 
+        self.draw_warmup(&mut chart, &style, size(15));
+        self.draw_baselines(&mut chart, &style);
+        self.draw_latency(&mut chart, &style);
+
+        chart
+            .configure_secondary_axes()
+            .axis_desc_style(
+                TextStyle::from(("sans-serif", size(30)).into_font()).color(&foreground),
+            )
+            .axis_style(ShapeStyle::from(foreground.mix(0.65)).stroke_width(size(1)))
+            .label_style(TextStyle::from(("sans-serif", size(15)).into_font()).color(&foreground))
+            .y_desc(match self.selected_metric() {
+                Some(_) => self.secondary_label(),
+                None => "Throughput (req/s)".to_owned(),
+            })
+            .y_labels(10)
+            .y_label_formatter(&|y: &f64| self.format_secondary(*y))
+            .draw()
+            .expect("failed to draw throughput axis");
+
+        self.draw_secondary(&mut chart, &style);
+        self.draw_stage_marks(&mut chart, &style, size(15));
+        self.draw_legend(&mut chart, &style, size(20));
+    }
+}
+
+impl ConnectionChart {
+    /// metric charted on the secondary axis, none when it shows throughput
+    fn selected_metric(&self) -> Option<&str> {
+        match &self.secondary {
+            SecondarySeries::Metric(name) if self.metric_names.contains(name) => Some(name),
+            _ => None,
+        }
+    }
+    /// name of the secondary axis, counters are charted per second
+    fn secondary_label(&self) -> String {
+        let Some(name) = self.selected_metric() else {
+            return "req/s".to_owned();
+        };
+
+        let counter = self
+            .main
+            .data_points
+            .iter()
+            .rev()
+            .find_map(|(_idx, interval)| {
+                interval
+                    .metrics
+                    .iter()
+                    .find(|(metric, _)| metric == name)
+                    .map(|(_, metric)| matches!(metric, Metric::Counter(_)))
+            });
+
+        match counter {
+            Some(true) => format!("{name}/s"),
+            _ => name.to_owned(),
+        }
+    }
+    /// small gauges keep their fractional part
+    fn format_secondary(&self, y: f64) -> String {
+        if self.secondary_range().end < 10.0 {
+            format!("{y:.2}")
+        } else {
+            format!("{y:.0}")
+        }
+    }
+    fn shown_phases(&self) -> impl Iterator<Item = (usize, &String)> {
+        self.phase_names
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| !self.hidden_phases.contains(name))
+    }
+    /// top edge of every shown phase stacked on the previous ones, empty when
+    /// the phases aren't stacked
+    fn stacked_phases(&self) -> Vec<Vec<(u64, u64)>> {
+        if !self.stack_phases {
+            return Vec::new();
+        }
+
+        let mut bottom: Vec<_> = self.main.data_points.iter().map(|x| (x.0, 0)).collect();
+
+        self.shown_phases()
+            .map(|(_, name)| {
+                let top: Vec<_> = self
+                    .main
+                    .data_points
+                    .iter()
+                    .zip(&bottom)
+                    .map(|((idx, interval), (_, bottom))| {
+                        let phase = interval.phase(name).map_or(0, |p| self.statistic.of(p));
+                        (*idx, bottom + phase)
+                    })
+                    .collect();
+
+                bottom = top.clone();
+                top
+            })
+            .collect()
+    }
+    /// selected statistic of the whole requests and of the phases marked by the plugin
+    fn draw_latency<DB: DrawingBackend>(
+        &self,
+        chart: &mut ChartContext<'_, DB, Cartesian2d<RangedCoordu64, RangedCoordu64>>,
+        style: &Style,
+    ) {
+        use plotters::prelude::*;
+
+        const P99_COLOR: RGBColor = RGBColor(0, 200, 200);
+
+        let plot_line_color = ConnectionChart::plot_color(0);
+        let latency = self
+            .main
+            .data_points
+            .iter()
+            .map(|x| (x.0, self.statistic.of(&x.1.percentiles)));
+
+        // stacked phases and the bands replace the area under the latency
+        let fill = if self.stack_phases && !self.phase_names.is_empty() || self.show_bands {
+            TRANSPARENT
+        } else {
+            plot_line_color.mix(0.175)
+        };
+        let label = match self.compared.is_empty() {
+            true => format!("latency ({})", self.statistic),
+            false => self.main.label.clone(),
+        };
+
+        if self.show_bands {
+            self.draw_bands(chart);
+        }
+
         chart
             .draw_series(
-                AreaSeries::new(
-                    self.data_points.iter().map(|x| (x.0, x.1)),
-                    0,
-                    PLOT_LINE_COLOR.mix(0.175),
-                )
-                .border_style(ShapeStyle::from(PLOT_LINE_COLOR).stroke_width(2)),
+                AreaSeries::new(latency, 0, fill)
+                    .border_style(ShapeStyle::from(plot_line_color).stroke_width(style.size(2))),
+            )
+            .expect("failed to draw chart data")
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], plot_line_color));
+
+        // the tail is drawn once unless it's the charted statistic
+        if self.show_p99 && self.statistic != Statistic::P99 {
+            let p99 = self
+                .main
+                .data_points
+                .iter()
+                .map(|x| (x.0, x.1.percentiles.p99));
+
+            chart
+                .draw_series(LineSeries::new(
+                    p99,
+                    ShapeStyle::from(P99_COLOR).stroke_width(style.size(1)),
+                ))
+                .expect("failed to draw p99")
+                .label("p99")
+                .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], P99_COLOR));
+        }
+
+        // compared targets are drawn as lines over the main one
+        for (index, series) in self.compared.iter().enumerate() {
+            let color = ConnectionChart::plot_color(index + 1);
+            let latency = series
+                .data_points
+                .iter()
+                .map(|x| (x.0, self.statistic.of(&x.1.percentiles)));
+
+            chart
+                .draw_series(LineSeries::new(
+                    latency,
+                    ShapeStyle::from(color).stroke_width(style.size(2)),
+                ))
+                .expect("failed to draw chart data")
+                .label(&series.label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+
+        if self.stack_phases {
+            let mut bottom: Vec<_> = self.main.data_points.iter().map(|x| (x.0, 0)).collect();
+
+            for ((index, name), top) in self.shown_phases().zip(self.stacked_phases()) {
+                let color = Palette99::pick(index).to_rgba();
+
+                // area between the previous phase and this one
+                let area = top.iter().copied().chain(bottom.iter().rev().copied());
+
+                chart
+                    .draw_series([Polygon::new(area.collect::<Vec<_>>(), color.mix(0.35))])
+                    .expect("failed to draw phases")
+                    .label(name)
+                    .legend(move |(x, y)| {
+                        Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.filled())
+                    });
+
+                bottom = top;
+            }
+
+            return;
+        }
+
+        for (index, name) in self.shown_phases() {
+            let color = Palette99::pick(index).to_rgba();
+            let phase = self.main.data_points.iter().map(|x| {
+                let phase = x.1.phase(name).map_or(0, |p| self.statistic.of(p));
+                (x.0, phase)
+            });
+
+            chart
+                .draw_series(LineSeries::new(
+                    phase,
+                    ShapeStyle::from(color).stroke_width(style.size(1)),
+                ))
+                .expect("failed to draw phases")
+                .label(name)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+    }
+    /// p10-p90 and the darker p25-p75 of the main target
+    fn draw_bands<DB: DrawingBackend>(
+        &self,
+        chart: &mut ChartContext<'_, DB, Cartesian2d<RangedCoordu64, RangedCoordu64>>,
+    ) {
+        use plotters::prelude::*;
+
+        let color = ConnectionChart::plot_color(0);
+        let bands = [
+            ("p10-p90", self.band(|p| p.p10, |p| p.p90), 0.12),
+            ("p25-p75", self.band(|p| p.p25, |p| p.p75), 0.22),
+        ];
+
+        for (label, outline, opacity) in bands {
+            let fill = color.mix(opacity);
+
+            chart
+                .draw_series([Polygon::new(outline, fill)])
+                .expect("failed to draw bands")
+                .label(label)
+                .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], fill.filled()));
+        }
+    }
+    /// outline of the area between two percentiles of the main target: the
+    /// upper one forwards, then the lower one backwards
+    fn band(
+        &self,
+        lower: impl Fn(&Percentiles) -> u64,
+        upper: impl Fn(&Percentiles) -> u64,
+    ) -> Vec<(u64, u64)> {
+        let points = &self.main.data_points;
+        let top = points
+            .iter()
+            .map(|(idx, interval)| (*idx, upper(&interval.percentiles)));
+        let bottom = points
+            .iter()
+            .rev()
+            .map(|(idx, interval)| (*idx, lower(&interval.percentiles)));
+
+        top.chain(bottom).collect()
+    }
+    /// dimmed latency of the saved tests behind the current one
+    fn draw_baselines<DB: DrawingBackend>(
+        &self,
+        chart: &mut ChartContext<'_, DB, Cartesian2d<RangedCoordu64, RangedCoordu64>>,
+        style: &Style,
+    ) {
+        use plotters::prelude::*;
+
+        for (index, baseline) in self.shown_baselines() {
+            let color = style.theme.baseline(index);
+
+            chart
+                .draw_series(LineSeries::new(
+                    self.baseline_points(baseline),
+                    ShapeStyle::from(color).stroke_width(style.size(2)),
+                ))
+                .expect("failed to draw baseline")
+                .label(&baseline.label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+    }
+    /// names of the series, only needed once the plugin marks phases, targets
+    /// are compared or baselines are loaded
+    fn draw_legend<'a, DB: DrawingBackend + 'a>(
+        &self,
+        chart: &mut ChartContext<'a, DB, Cartesian2d<RangedCoordu64, RangedCoordu64>>,
+        style: &Style,
+        font_size: u32,
+    ) {
+        use plotters::prelude::*;
+
+        if self.phase_names.is_empty()
+            && self.compared.is_empty()
+            && self.shown_baselines().next().is_none()
+            && !self.show_bands
+            && !self.show_p99
+        {
+            return;
+        }
+
+        let foreground = style.theme.foreground();
+
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(style.theme.background().mix(0.8))
+            .border_style(foreground.mix(0.3))
+            .label_font(TextStyle::from(("sans-serif", font_size).into_font()).color(&foreground))
+            .draw()
+            .expect("failed to draw legend");
+    }
+    /// completed and failed requests per second or the selected plugin metric
+    /// on the secondary axis
+    fn draw_secondary<DB: DrawingBackend>(
+        &self,
+        chart: &mut DualCoordChartContext<
+            '_,
+            DB,
+            Cartesian2d<RangedCoordu64, RangedCoordu64>,
+            Cartesian2d<RangedCoordu64, RangedCoordf64>,
+        >,
+        style: &Style,
+    ) {
+        use plotters::prelude::*;
+
+        const THROUGHPUT_COLOR: RGBColor = RGBColor(255, 170, 0);
+        const FAILED_COLOR: RGBColor = RGBColor(255, 75, 75);
+        const METRIC_COLOR: RGBColor = RGBColor(190, 120, 255);
+
+        // every target gets a dashed line in its colour
+        if !self.compared.is_empty() {
+            let metric = self.selected_metric();
+
+            for (index, series) in self.series().enumerate() {
+                let line = ShapeStyle::from(ConnectionChart::plot_color(index))
+                    .stroke_width(style.size(1));
+                let points = series.data_points.iter().filter_map(|x| match metric {
+                    Some(name) => x.1.metric(name).map(|value| (x.0, value)),
+                    None => Some((x.0, x.1.throughput())),
+                });
+
+                chart
+                    .draw_secondary_series(DashedLineSeries::new(
+                        points,
+                        style.size(6),
+                        style.size(4),
+                        line,
+                    ))
+                    .expect("failed to draw throughput");
+            }
+
+            return;
+        }
+
+        if let Some(name) = self.selected_metric() {
+            chart
+                .draw_secondary_series(LineSeries::new(
+                    self.main
+                        .data_points
+                        .iter()
+                        .filter_map(|x| x.1.metric(name).map(|value| (x.0, value))),
+                    ShapeStyle::from(METRIC_COLOR).stroke_width(style.size(2)),
+                ))
+                .expect("failed to draw metric");
+
+            return;
+        }
+
+        chart
+            .draw_secondary_series(LineSeries::new(
+                self.main
+                    .data_points
+                    .iter()
+                    .map(|x| (x.0, x.1.throughput())),
+                ShapeStyle::from(THROUGHPUT_COLOR).stroke_width(style.size(2)),
+            ))
+            .expect("failed to draw throughput");
+
+        if self.main.total.errors.total() == 0 {
+            return;
+        }
+
+        chart
+            .draw_secondary_series(LineSeries::new(
+                self.main
+                    .data_points
+                    .iter()
+                    .map(|x| (x.0, x.1.failed_throughput())),
+                ShapeStyle::from(FAILED_COLOR).stroke_width(style.size(2)),
+            ))
+            .expect("failed to draw throughput");
+    }
+    /// shaded area behind the points of the warm-up
+    fn draw_warmup<DB: DrawingBackend>(
+        &self,
+        chart: &mut ChartContext<'_, DB, Cartesian2d<RangedCoordu64, RangedCoordu64>>,
+        style: &Style,
+        font_size: u32,
+    ) {
+        use plotters::prelude::*;
+
+        let mut warmup = self
+            .main
+            .data_points
+            .iter()
+            .filter(|(_idx, interval)| interval.warmup)
+            .map(|(idx, _)| *idx);
+        let Some(first) = warmup.next() else { return };
+        let last = warmup.next_back().unwrap_or(first);
+
+        // the point closes the interval, the area starts where it began
+        let start = std::cmp::max(first.saturating_sub(1), chart.x_range().start);
+        let y_end = chart.y_range().end;
+        let foreground = style.theme.foreground();
+        let font_color = foreground.mix(0.7);
+        let font = TextStyle::from(("sans-serif", font_size).into_font()).color(&font_color);
+
+        chart
+            .draw_series([Rectangle::new(
+                [(start, 0), (last, y_end)],
+                foreground.mix(0.06).filled(),
+            )])
+            .expect("failed to draw warm-up");
+
+        // the legend and the stage names take the top of the chart
+        chart
+            .draw_series([EmptyElement::at((start, 0))
+                + Text::new("warm-up", (4, -(font_size as i32) - 4), font)])
+            .expect("failed to draw warm-up");
+    }
+    /// vertical line with the stage name at the start of every profile stage
+    fn draw_stage_marks<DB: DrawingBackend>(
+        &self,
+        chart: &mut ChartContext<'_, DB, Cartesian2d<RangedCoordu64, RangedCoordu64>>,
+        style: &Style,
+        font_size: u32,
+    ) {
+        use plotters::prelude::*;
+
+        let Some(profile) = &self.profile else { return };
+        let y_end = chart.y_range().end;
+        let foreground = style.theme.foreground();
+        let line = ShapeStyle::from(foreground.mix(0.4)).stroke_width(style.size(1));
+        let font_color = foreground.mix(0.7);
+        let font = TextStyle::from(("sans-serif", font_size).into_font()).color(&font_color);
+        let boundaries = profile.boundaries();
+
+        chart
+            .draw_series(
+                boundaries.iter().map(|(second, _)| {
+                    PathElement::new(vec![(*second, 0), (*second, y_end)], line)
+                }),
             )
-            .expect("failed to draw chart data");
+            .expect("failed to draw stage marks");
+
+        chart
+            .draw_series(boundaries.into_iter().map(|(second, label)| {
+                EmptyElement::at((second, y_end)) + Text::new(label, (4, 4), font.clone())
+            }))
+            .expect("failed to draw stage marks");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export::{IntervalRow, TargetResults},
+        fixtures::interval,
+    };
+
+    #[test]
+    fn baselines_from_saved_test() {
+        let export = Export {
+            mode: benchmark::Mode::Increase,
+            targets: ["A", "B"]
+                .into_iter()
+                .map(|label| TargetResults {
+                    label: label.to_owned(),
+                    requests: Default::default(),
+                    intervals: (1..=3)
+                        .map(|index| IntervalRow::new(index, &interval(index)))
+                        .collect(),
+                })
+                .collect(),
+        };
+        // the points are read back from the milliseconds saved in json
+        let json = serde_json::to_string(&export).unwrap();
+        let baselines = Baseline::from_export("old.json", serde_json::from_str(&json).unwrap());
+
+        assert_eq!(baselines.len(), 2);
+        assert_eq!(baselines[0].label, "old.json A");
+        assert_eq!(baselines[1].label, "old.json B");
+        assert!(baselines.iter().all(|baseline| baseline.increase));
+        assert_eq!(
+            baselines[0].points,
+            (1..=3)
+                .map(|index| (index, interval(index).percentiles))
+                .collect::<Vec<_>>()
+        );
     }
 }
@@ -0,0 +1,65 @@
+use super::*;
+use crate::{benchmark, target::Target};
+use iced::widget::column;
+use std::sync::Arc;
+
+/// server and plugin of one side of the A/B test
+#[derive(Clone, Debug)]
+pub struct Variant {
+    pub target: Target,
+    pub plugin_name: String,
+    pub parameters: Arc<benchmark::Parameters>,
+}
+
+impl Variant {
+    /// name on the chart, targets are lettered in the order they're run
+    pub fn label(&self, index: usize) -> String {
+        let letter = (b'A' + (index % 26) as u8) as char;
+
+        format!("{letter}: {} ({})", self.target, self.plugin_name)
+    }
+}
+
+/// targets run next to the one set in the inputs
+#[derive(Default)]
+pub struct Compare {
+    pub variants: Vec<Variant>,
+}
+
+impl Compare {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        use Message::*;
+
+        match message {
+            CompareRemove(index) if index < self.variants.len() => {
+                self.variants.remove(index);
+            }
+            _ => {}
+        }
+
+        Task::none()
+    }
+    pub fn view(&self, test_running: bool) -> Element<'_, Message> {
+        // the inputs are the first target, the added ones follow it
+        let variants = self.variants.iter().enumerate().map(|(index, variant)| {
+            row![
+                container(text(variant.label(index + 1))).padding(Padding::default().top(4)),
+                button("x")
+                    .on_press_maybe((!test_running).then_some(Message::CompareRemove(index)))
+                    .style(secondary),
+            ]
+            .spacing(5)
+            .into()
+        });
+
+        column![
+            text!("Compare with:"),
+            button("Add current target")
+                .on_press_maybe((!test_running).then_some(Message::CompareAdd))
+                .style(secondary),
+        ]
+        .extend(variants)
+        .spacing(5)
+        .into()
+    }
+}
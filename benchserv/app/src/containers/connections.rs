@@ -48,14 +48,18 @@ impl Connections {
             _ => Task::none(),
         }
     }
-    pub fn view(&self, constant: bool) -> Element<Message> {
-        let text = if constant {
+    pub fn view(&self, mode: &Mode) -> Element<'_, Message> {
+        let text = if mode.constant {
             "Connections Number"
-        } else {
+        } else if mode.increase {
             "Max connections"
+        } else {
+            "Requests per second"
         };
 
-        let err = if self.text_state.incorrect {
+        let err = if self.text_state.incorrect && mode.rate {
+            "Bad requests rate"
+        } else if self.text_state.incorrect {
             "Bad connections number"
         } else {
             ""
@@ -0,0 +1,99 @@
+use super::*;
+use iced::widget::column;
+
+/// size and theme of the saved chart
+pub struct Image {
+    pub width: TextInputState,
+    pub height: TextInputState,
+    pub dpi: TextInputState,
+    pub light: bool,
+}
+
+impl Default for Image {
+    fn default() -> Self {
+        Image {
+            width: TextInputState::new("1920".to_owned(), false),
+            height: TextInputState::new("1080".to_owned(), false),
+            dpi: TextInputState::new(ImageOptions::DEFAULT_DPI.to_string(), false),
+            light: false,
+        }
+    }
+}
+
+impl Image {
+    fn check_size(size: &str) -> bool {
+        matches!(size.parse::<u32>(), Ok(100..=20_000))
+    }
+    fn check_dpi(dpi: &str) -> bool {
+        matches!(dpi.parse::<u32>(), Ok(24..=1200))
+    }
+    pub fn incorrect(&self) -> bool {
+        self.width.incorrect || self.height.incorrect || self.dpi.incorrect
+    }
+    /// none until all inputs are correct
+    pub fn value(&self) -> Option<ImageOptions> {
+        if self.incorrect() {
+            return None;
+        }
+
+        Some(ImageOptions {
+            width: self.width.content.parse().ok()?,
+            height: self.height.content.parse().ok()?,
+            dpi: self.dpi.content.parse().ok()?,
+            theme: if self.light {
+                ChartTheme::Light
+            } else {
+                ChartTheme::Dark
+            },
+        })
+    }
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        use Message::*;
+
+        match message {
+            ImageWidthChanged(new_content) => {
+                self.width.incorrect = !Image::check_size(&new_content);
+                self.width.content = new_content;
+            }
+            ImageHeightChanged(new_content) => {
+                self.height.incorrect = !Image::check_size(&new_content);
+                self.height.content = new_content;
+            }
+            ImageDpiChanged(new_content) => {
+                self.dpi.incorrect = !Image::check_dpi(&new_content);
+                self.dpi.content = new_content;
+            }
+            LightThemeChanged(light) => self.light = light,
+            _ => {}
+        }
+
+        Task::none()
+    }
+    pub fn view(&self) -> Element<'_, Message> {
+        let image_err = if self.incorrect() {
+            "Bad image size"
+        } else {
+            ""
+        };
+
+        column![
+            text!("Saved image:"),
+            row![
+                text_input("Width", &self.width.content)
+                    .on_input(Message::ImageWidthChanged)
+                    .width(60),
+                text_input("Height", &self.height.content)
+                    .on_input(Message::ImageHeightChanged)
+                    .width(60),
+                text_input("DPI", &self.dpi.content)
+                    .on_input(Message::ImageDpiChanged)
+                    .width(50),
+                checkbox("Light", self.light).on_toggle(Message::LightThemeChanged),
+            ]
+            .align_y(Alignment::Center)
+            .spacing(5),
+            text(image_err).color(Color::from_rgb(255.0, 0.0, 0.0))
+        ]
+        .into()
+    }
+}
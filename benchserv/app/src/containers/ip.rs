@@ -1,10 +1,41 @@
 use super::*;
+use crate::target;
 use iced::widget::column;
-pub struct Ip(pub TextInputState);
+use std::{fmt::Display, net::IpAddr, time::Duration};
+
+/// address used when the host name resolves to more than one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressChoice {
+    /// every new connection uses the next address
+    Rotate,
+    Single(IpAddr),
+}
+
+impl Display for AddressChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressChoice::Rotate => write!(f, "Rotate all"),
+            AddressChoice::Single(ip) => write!(f, "{ip}"),
+        }
+    }
+}
+
+pub struct Ip {
+    pub input: TextInputState,
+    /// host name the addresses belong to
+    pub resolved_host: String,
+    pub addresses: Result<Vec<IpAddr>, String>,
+    pub choice: AddressChoice,
+}
 
 impl Default for Ip {
     fn default() -> Self {
-        Ip(TextInputState::new("127.0.0.1".to_owned(), false))
+        Ip {
+            input: TextInputState::new("127.0.0.1".to_owned(), false),
+            resolved_host: String::new(),
+            addresses: Ok(Vec::new()),
+            choice: AddressChoice::Rotate,
+        }
     }
 }
 
@@ -12,33 +43,40 @@ impl Deref for Ip {
     type Target = TextInputState;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.input
     }
 }
 
 impl DerefMut for Ip {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.input
     }
 }
 
 impl Ip {
+    /// the name is looked up once the user stops typing
+    const RESOLVE_DELAY: Duration = Duration::from_millis(500);
+
     fn check(&self, ip: &str) -> bool {
-        if ip == "localhost" {
-            return true;
+        target::is_hostname(ip) || parse_ip(ip).is_some()
+    }
+    pub fn is_hostname(&self) -> bool {
+        target::is_hostname(&self.content)
+    }
+    /// addresses chosen by the user, none until the host name is resolved
+    pub fn chosen_addresses(&self) -> Option<Vec<IpAddr>> {
+        if !self.is_hostname() {
+            return parse_ip(&self.content).map(|ip| vec![ip]);
         }
-
-        let split = ip.split(".").collect::<Vec<_>>();
-
-        if split.len() < 4 {
-            return false;
+        if self.resolved_host != self.content {
+            return None;
         }
 
-        if split.iter().any(|octet| octet.parse::<u8>().is_err()) {
-            return false;
+        let addresses = self.addresses.as_ref().ok()?;
+        match self.choice {
+            AddressChoice::Rotate => Some(addresses.clone()),
+            AddressChoice::Single(ip) => Some(vec![ip]),
         }
-
-        return true;
     }
     pub fn update(&mut self, message: Message) -> Task<Message> {
         use Message::*;
@@ -53,23 +91,101 @@ impl Ip {
 
                 self.content = new_content;
 
+                if !self.is_hostname() {
+                    return Task::none();
+                }
+
+                let host = self.content.clone();
+                Task::perform(tokio::time::sleep(Ip::RESOLVE_DELAY), move |_| {
+                    Message::ResolveHost(host.clone())
+                })
+            }
+            // the name was changed again while waiting
+            ResolveHost(host) if host == self.content => {
+                Task::perform(target::resolve(host.clone()), move |addresses| {
+                    Message::HostResolved(host.clone(), addresses)
+                })
+            }
+            // answers for the names typed before are dropped
+            HostResolved(host, addresses) if host == self.content => {
+                // ipv4 first, most plugins only support it
+                self.choice = match &addresses {
+                    Ok(addresses) => addresses
+                        .iter()
+                        .find(|ip| ip.is_ipv4())
+                        .or(addresses.first())
+                        .map_or(AddressChoice::Rotate, |ip| AddressChoice::Single(*ip)),
+                    Err(_) => AddressChoice::Rotate,
+                };
+                self.resolved_host = host;
+                self.addresses = addresses;
+
+                Task::none()
+            }
+            AddressChoiceChanged(choice) => {
+                self.choice = choice;
                 Task::none()
             }
             _ => Task::none(),
         }
     }
-    pub fn view(&self) -> Element<Message> {
-        let ip_err = if self.incorrect { "Bad ip address" } else { "" };
+    pub fn view(&self) -> Element<'_, Message> {
+        let ip_err = if self.incorrect {
+            "Bad address or host name"
+        } else {
+            ""
+        };
 
         column![
             text!("Server IP:"),
-            text_input("Server's IP", &self.content)
+            text_input("IP, [IPv6] or host name", &self.content)
                 .on_input(Message::IpChanged)
                 .width(170),
-            text(ip_err).color(Color::from_rgb(255.0, 0.0, 0.0))
         ]
+        .push_maybe(self.addresses_view())
+        .push(text(ip_err).color(Color::from_rgb(255.0, 0.0, 0.0)))
         .into()
     }
+    /// addresses of the host name to choose from
+    fn addresses_view(&self) -> Option<Element<'_, Message>> {
+        if self.incorrect || !self.is_hostname() {
+            return None;
+        }
+        if self.resolved_host != self.content {
+            return Some(text("Resolving...").into());
+        }
+
+        let view = match &self.addresses {
+            Ok(addresses) if addresses.is_empty() => text("No addresses found")
+                .color(Color::from_rgb(255.0, 0.0, 0.0))
+                .into(),
+            Ok(addresses) => {
+                let options: Vec<_> = std::iter::once(AddressChoice::Rotate)
+                    .chain(addresses.iter().copied().map(AddressChoice::Single))
+                    .collect();
+
+                pick_list(options, Some(self.choice), Message::AddressChoiceChanged)
+                    .width(170)
+                    .into()
+            }
+            Err(err) => text!("Can't resolve: {err}")
+                .color(Color::from_rgb(255.0, 0.0, 0.0))
+                .width(170)
+                .into(),
+        };
+
+        Some(view)
+    }
+}
+
+/// `127.0.0.1`, `::1` or bracketed `[::1]`
+fn parse_ip(ip: &str) -> Option<IpAddr> {
+    let ip = ip
+        .strip_prefix('[')
+        .and_then(|ip| ip.strip_suffix(']'))
+        .unwrap_or(ip);
+
+    ip.parse().ok()
 }
 
 pub struct Port(pub TextInputState);
@@ -0,0 +1,63 @@
+use super::*;
+use crate::{benchmark, profile};
+use iced::widget::column;
+
+/// duration and number of requests ending the constant and rate modes, empty
+/// input means no limit
+#[derive(Default)]
+pub struct Limits {
+    pub duration: TextInputState,
+    pub requests: TextInputState,
+}
+
+impl Limits {
+    fn check_requests(requests: &str) -> bool {
+        matches!(requests.parse::<u64>(), Ok(1..))
+    }
+    pub fn incorrect(&self) -> bool {
+        self.duration.incorrect || self.requests.incorrect
+    }
+    pub fn value(&self) -> benchmark::Limits {
+        benchmark::Limits {
+            duration: profile::parse_duration(&self.duration.content).ok(),
+            requests: self.requests.content.parse().ok(),
+        }
+    }
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        use Message::*;
+
+        match message {
+            DurationLimitChanged(new_content) => {
+                self.duration.incorrect =
+                    !new_content.is_empty() && profile::parse_duration(&new_content).is_err();
+                self.duration.content = new_content;
+            }
+            RequestsLimitChanged(new_content) => {
+                self.requests.incorrect =
+                    !new_content.is_empty() && !Limits::check_requests(&new_content);
+                self.requests.content = new_content;
+            }
+            _ => {}
+        }
+
+        Task::none()
+    }
+    pub fn view(&self) -> Element<'_, Message> {
+        let limits_err = if self.incorrect() { "Bad limit" } else { "" };
+
+        column![
+            text!("Stop after:"),
+            row![
+                text_input("Duration", &self.duration.content)
+                    .on_input(Message::DurationLimitChanged)
+                    .width(90),
+                text_input("Requests", &self.requests.content)
+                    .on_input(Message::RequestsLimitChanged)
+                    .width(90),
+            ]
+            .spacing(5),
+            text(limits_err).color(Color::from_rgb(255.0, 0.0, 0.0))
+        ]
+        .into()
+    }
+}
@@ -7,6 +7,7 @@ use iced::{
         checkbox, container, pick_list, row, slider, text, text_input,
     },
 };
+use rfd::AsyncFileDialog;
 use std::{
     default::Default,
     ops::{Deref, DerefMut},
@@ -14,17 +15,33 @@ use std::{
 
 pub mod buttons;
 pub mod chart;
+pub mod compare;
 pub mod connections;
+pub mod image;
 pub mod ip;
+pub mod limits;
 pub mod mode;
+pub mod parameters;
 pub mod plugins;
+pub mod precision;
+pub mod profile;
+pub mod timeouts;
+pub mod warmup;
 
 pub use buttons::*;
 pub use chart::*;
+pub use compare::*;
 pub use connections::*;
+pub use image::*;
 pub use ip::*;
+pub use limits::*;
 pub use mode::*;
+pub use parameters::*;
 pub use plugins::*;
+pub use precision::*;
+pub use profile::*;
+pub use timeouts::*;
+pub use warmup::*;
 
 #[derive(Default)]
 pub struct TextInputState {
@@ -1,21 +1,38 @@
 use super::*;
+use crate::benchmark;
 use iced::widget::column;
 
 #[derive(Default)]
 pub struct Mode {
     pub constant: bool,
     pub increase: bool,
+    pub rate: bool,
+    pub profile: bool,
 }
 
 impl Mode {
     pub fn chosen(&self) -> bool {
-        self.constant | self.increase
+        self.constant | self.increase | self.rate | self.profile
+    }
+    /// chosen mode as written to the exported results
+    pub fn value(&self) -> benchmark::Mode {
+        if self.constant {
+            benchmark::Mode::Constant
+        } else if self.increase {
+            benchmark::Mode::Increase
+        } else if self.rate {
+            benchmark::Mode::Rate
+        } else {
+            benchmark::Mode::Profile
+        }
     }
     pub fn view(&self) -> Element<Message> {
         column![
             text!("Test mode: "),
             checkbox("Constant connections", self.constant).on_toggle(Message::ConstantModeChanged),
-            checkbox("Increase connections", self.increase).on_toggle(Message::IncreaseModeChanged)
+            checkbox("Increase connections", self.increase).on_toggle(Message::IncreaseModeChanged),
+            checkbox("Constant arrival rate", self.rate).on_toggle(Message::RateModeChanged),
+            checkbox("Load profile", self.profile).on_toggle(Message::ProfileModeChanged)
         ]
         .into()
     }
@@ -24,10 +41,14 @@ impl Mode {
 
         self.constant = false;
         self.increase = false;
+        self.rate = false;
+        self.profile = false;
 
         match message {
             ConstantModeChanged(val) => self.constant = val,
             IncreaseModeChanged(val) => self.increase = val,
+            RateModeChanged(val) => self.rate = val,
+            ProfileModeChanged(val) => self.profile = val,
             _ => unreachable!(),
         }
 
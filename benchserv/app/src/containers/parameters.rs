@@ -0,0 +1,113 @@
+use super::*;
+use crate::benchmark;
+use iced::widget::{column, tooltip};
+use interface::{Parameter, ParameterKind};
+
+/// form generated from the parameters declared by the selected plugin
+#[derive(Default)]
+pub struct PluginParameters {
+    pub schema: Vec<Parameter>,
+    pub values: Vec<TextInputState>,
+}
+
+impl PluginParameters {
+    /// replaces the form, every field starts with its default value
+    pub fn set_schema(&mut self, schema: Vec<Parameter>) {
+        self.values = schema
+            .iter()
+            .map(|parameter| TextInputState::new(parameter.default.to_string(), false))
+            .collect();
+        self.schema = schema;
+    }
+    /// replaces the form of the same plugin, fields with unchanged name and
+    /// type keep their values
+    pub fn update_schema(&mut self, schema: Vec<Parameter>) {
+        self.values = schema
+            .iter()
+            .map(|parameter| {
+                self.schema
+                    .iter()
+                    .zip(&self.values)
+                    .find(|(old, _)| old.name == parameter.name && old.kind == parameter.kind)
+                    .map_or_else(
+                        || TextInputState::new(parameter.default.to_string(), false),
+                        |(_, value)| TextInputState::new(value.content.clone(), value.incorrect),
+                    )
+            })
+            .collect();
+        self.schema = schema;
+    }
+    pub fn incorrect(&self) -> bool {
+        self.values.iter().any(|value| value.incorrect)
+    }
+    pub fn value(&self) -> benchmark::Parameters {
+        self.schema
+            .iter()
+            .zip(&self.values)
+            .map(|(parameter, value)| (parameter.name.to_string(), value.content.clone()))
+            .collect()
+    }
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        use Message::*;
+
+        match message {
+            ParameterChanged(index, content) => {
+                let kind = self.schema[index].kind;
+                let value = &mut self.values[index];
+
+                value.incorrect = !kind.is_valid(&content);
+                value.content = content;
+
+                Task::none()
+            }
+            _ => Task::none(),
+        }
+    }
+    pub fn view(&self) -> Element<'_, Message> {
+        if self.schema.is_empty() {
+            return column![].into();
+        }
+
+        let fields =
+            self.schema
+                .iter()
+                .zip(&self.values)
+                .enumerate()
+                .map(|(index, (parameter, value))| {
+                    let input: Element<'_, Message> = match parameter.kind {
+                        ParameterKind::Bool => checkbox("", value.content == "true")
+                            .on_toggle(move |checked| {
+                                Message::ParameterChanged(index, checked.to_string())
+                            })
+                            .into(),
+                        _ => text_input(parameter.default.as_str(), &value.content)
+                            .on_input(move |content| Message::ParameterChanged(index, content))
+                            .width(120)
+                            .into(),
+                    };
+
+                    row![
+                        tooltip(
+                            text!("{}: ", parameter.name),
+                            container(text(parameter.description.as_str()))
+                                .style(container::rounded_box)
+                                .padding(5),
+                            tooltip::Position::Bottom,
+                        ),
+                        input
+                    ]
+                    .into()
+                });
+
+        let err = if self.incorrect() {
+            "Bad parameter value"
+        } else {
+            ""
+        };
+
+        column![text!("Plugin parameters:")]
+            .extend(fields)
+            .push(text(err).color(Color::from_rgb(255.0, 0.0, 0.0)))
+            .into()
+    }
+}
@@ -1,13 +1,42 @@
 use super::*;
-use iced::widget::column;
+use crate::plugins::PluginInfo;
+use iced::widget::{column, tooltip};
 
+/// lines of the loader error shown in the tooltip
+const ERROR_LINES: usize = 12;
+
+/// plugins found in the plugins directory, the ones that can't be loaded are
+/// shown greyed out with the reason
 #[derive(Default)]
 pub struct PluginsSelect {
-    pub all: Vec<String>,
+    pub all: Vec<PluginInfo>,
     pub selected: String,
+    /// rescan the directory when libraries in it change
+    pub watch: bool,
 }
 
 impl PluginsSelect {
+    /// replaces the list, keeps the selection if the plugin is still usable
+    pub fn set_plugins(&mut self, plugins: Vec<PluginInfo>) {
+        let still_usable = plugins
+            .iter()
+            .any(|plugin| plugin.name == self.selected && plugin.compatible());
+
+        if !still_usable {
+            self.selected = plugins
+                .iter()
+                .find(|plugin| plugin.compatible())
+                .map(|plugin| plugin.name.clone())
+                .unwrap_or_default();
+        }
+        self.all = plugins;
+    }
+    /// selected plugin, none when there is no usable plugin
+    pub fn selected(&self) -> Option<&PluginInfo> {
+        self.all
+            .iter()
+            .find(|plugin| plugin.name == self.selected && plugin.compatible())
+    }
     pub fn update(&mut self, message: Message) -> Task<Message> {
         use Message::*;
 
@@ -16,14 +45,107 @@ impl PluginsSelect {
                 self.selected = selected;
                 Task::none()
             }
+            WatchPluginsChanged(watch) => {
+                self.watch = watch;
+                Task::none()
+            }
+            OpenPluginsFolder => {
+                let task = async {
+                    let folder = AsyncFileDialog::new().pick_folder().await?;
+
+                    Some(folder.path().to_string_lossy().into_owned())
+                };
+
+                Task::perform(task, Message::PluginsFolderOpened)
+            }
             _ => Task::none(),
         }
     }
-    pub fn view(&self) -> Element<Message> {
-        column![
-            text("Select plugin:"),
-            pick_list(&self.all[..], Some(&self.selected), Message::PluginChange)
-        ]
-        .into()
+    pub fn view(&self) -> Element<'_, Message> {
+        let plugins = self.all.iter().map(|plugin| {
+            let content: Element<'_, Message> = match &plugin.error {
+                None => {
+                    let style = if plugin.name == self.selected {
+                        primary
+                    } else {
+                        secondary
+                    };
+
+                    button(text(plugin.title()))
+                        .on_press(Message::PluginChange(plugin.name.clone()))
+                        .style(style)
+                        .into()
+                }
+                // button without a message is drawn as disabled
+                Some(err) => row![
+                    button(text(plugin.title())).style(secondary),
+                    text(&err.summary).color(Color::from_rgb(0.5, 0.5, 0.5))
+                ]
+                .spacing(5)
+                .align_y(Alignment::Center)
+                .into(),
+            };
+
+            tooltip(
+                content,
+                container(text(Self::describe(plugin)))
+                    .style(container::rounded_box)
+                    .max_width(500)
+                    .padding(5),
+                tooltip::Position::Bottom,
+            )
+            .into()
+        });
+
+        let empty = self.all.is_empty().then(|| text("No plugins found"));
+
+        column![text("Select plugin:")]
+            .extend(plugins)
+            .push_maybe(empty)
+            .push(
+                row![
+                    button(text("Rescan").size(12))
+                        .on_press(Message::RescanPlugins)
+                        .style(secondary),
+                    button(text("Plugins folder...").size(12))
+                        .on_press(Message::OpenPluginsFolder)
+                        .style(secondary),
+                ]
+                .spacing(5),
+            )
+            .push(
+                checkbox("Watch folder", self.watch)
+                    .on_toggle(Message::WatchPluginsChanged)
+                    .text_size(12)
+                    .size(12),
+            )
+            .spacing(2)
+            .into()
+    }
+    /// metadata of the plugin or the end of the loader error
+    fn describe(plugin: &PluginInfo) -> String {
+        if let Some(err) = &plugin.error {
+            // layout errors list the whole interface, the reason is at the end
+            let lines: Vec<_> = err.details.trim().lines().collect();
+            let shown = &lines[lines.len().saturating_sub(ERROR_LINES)..];
+
+            return format!("{}: {}\n{}", plugin.name, err.summary, shown.join("\n"));
+        }
+
+        let metadata = &plugin.metadata;
+        let mut lines = vec![format!("file: {}", plugin.name)];
+
+        if !metadata.description.is_empty() {
+            lines.push(metadata.description.to_string());
+        }
+        if !metadata.author.is_empty() {
+            lines.push(format!("author: {}", metadata.author));
+        }
+        if !metadata.version.is_empty() {
+            lines.push(format!("version: {}", metadata.version));
+        }
+        lines.push(format!("addresses: {}", metadata.families));
+
+        lines.join("\n")
     }
 }
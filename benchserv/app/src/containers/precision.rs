@@ -0,0 +1,69 @@
+use super::*;
+use crate::stats::{DEFAULT_PRECISION, MAX_PRECISION};
+use iced::widget::column;
+
+/// number of significant figures kept by the latency histograms
+pub struct Precision(pub TextInputState);
+
+impl Default for Precision {
+    fn default() -> Self {
+        Precision(TextInputState::new(DEFAULT_PRECISION.to_string(), false))
+    }
+}
+
+impl Deref for Precision {
+    type Target = TextInputState;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Precision {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Precision {
+    fn check(&self, precision: &str) -> bool {
+        matches!(precision.parse::<u8>(), Ok(1..=MAX_PRECISION))
+    }
+    pub fn value(&self) -> u8 {
+        self.content.parse().unwrap_or(DEFAULT_PRECISION)
+    }
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        use Message::*;
+
+        match message {
+            PrecisionChanged(new_content) => {
+                self.incorrect = false;
+
+                if !new_content.is_empty() && !self.check(&new_content) {
+                    self.incorrect = true;
+                }
+
+                self.content = new_content;
+
+                Task::none()
+            }
+            _ => Task::none(),
+        }
+    }
+    pub fn view(&self) -> Element<'_, Message> {
+        let precision_err = if self.incorrect {
+            "Precision must be 1-5"
+        } else {
+            ""
+        };
+
+        column![
+            text!("Precision (digits):"),
+            text_input("3", &self.content)
+                .on_input(Message::PrecisionChanged)
+                .width(90),
+            text(precision_err).color(Color::from_rgb(255.0, 0.0, 0.0))
+        ]
+        .into()
+    }
+}
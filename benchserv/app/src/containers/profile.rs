@@ -0,0 +1,74 @@
+use super::*;
+use crate::profile;
+use iced::widget::column;
+
+/// load profile stages, typed in or opened from a file
+#[derive(Default)]
+pub struct Profile {
+    pub text_state: TextInputState,
+    pub value: Option<profile::Profile>,
+    error: String,
+}
+
+impl Profile {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        use Message::*;
+
+        match message {
+            ProfileOpened(Some(content)) | ProfileChanged(content) => {
+                match content.parse::<profile::Profile>() {
+                    Ok(profile) => {
+                        // files can have stages in separate lines
+                        self.text_state.content = if content.contains('\n') {
+                            profile.to_string()
+                        } else {
+                            content
+                        };
+                        self.value = Some(profile);
+                        self.error.clear();
+                    }
+                    Err(err) => {
+                        self.text_state.content = content;
+                        self.value = None;
+                        self.error = err;
+                    }
+                }
+                self.text_state.incorrect = self.value.is_none();
+
+                Task::none()
+            }
+            OpenProfile => {
+                let task = async {
+                    let file = AsyncFileDialog::new()
+                        .add_filter("profile", &["txt", "profile"])
+                        .pick_file()
+                        .await?;
+
+                    Some(String::from_utf8_lossy(&file.read().await).into_owned())
+                };
+
+                Task::perform(task, Message::ProfileOpened)
+            }
+            _ => Task::none(),
+        }
+    }
+    pub fn view(&self) -> Element<'_, Message> {
+        column![
+            text!("Load profile: "),
+            row![
+                text_input(
+                    "ramp 500 60s; hold 2m; spike 2000 10s; ramp 0 30s",
+                    &self.text_state.content
+                )
+                .on_input(Message::ProfileChanged)
+                .width(360),
+                button("Open")
+                    .on_press(Message::OpenProfile)
+                    .style(secondary),
+            ]
+            .spacing(5),
+            text!("{}", self.error).color(Color::from_rgb(255.0, 0.0, 0.0))
+        ]
+        .into()
+    }
+}
@@ -0,0 +1,69 @@
+use super::*;
+use crate::benchmark;
+use iced::widget::column;
+use std::time::Duration;
+
+/// request, connection and stop drain timeouts in milliseconds, empty input
+/// means no timeout
+#[derive(Default)]
+pub struct Timeouts {
+    pub request: TextInputState,
+    pub connection: TextInputState,
+    /// requests in progress are aborted right away on stop without it
+    pub drain: TextInputState,
+}
+
+impl Timeouts {
+    fn check(timeout: &str) -> bool {
+        matches!(timeout.parse::<u64>(), Ok(1..))
+    }
+    fn parse(timeout: &str) -> Option<Duration> {
+        timeout.parse().ok().map(Duration::from_millis)
+    }
+    pub fn incorrect(&self) -> bool {
+        self.request.incorrect || self.connection.incorrect || self.drain.incorrect
+    }
+    pub fn value(&self) -> benchmark::Timeouts {
+        benchmark::Timeouts {
+            request: Timeouts::parse(&self.request.content),
+            connection: Timeouts::parse(&self.connection.content),
+            drain: Timeouts::parse(&self.drain.content),
+        }
+    }
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        use Message::*;
+
+        let (state, new_content) = match message {
+            RequestTimeoutChanged(new_content) => (&mut self.request, new_content),
+            ConnectionTimeoutChanged(new_content) => (&mut self.connection, new_content),
+            DrainTimeoutChanged(new_content) => (&mut self.drain, new_content),
+            _ => return Task::none(),
+        };
+
+        state.incorrect = !new_content.is_empty() && !Timeouts::check(&new_content);
+        state.content = new_content;
+
+        Task::none()
+    }
+    pub fn view(&self) -> Element<'_, Message> {
+        let timeout_err = if self.incorrect() { "Bad timeout" } else { "" };
+
+        column![
+            text!("Timeouts (ms):"),
+            row![
+                text_input("Request", &self.request.content)
+                    .on_input(Message::RequestTimeoutChanged)
+                    .width(90),
+                text_input("Connection", &self.connection.content)
+                    .on_input(Message::ConnectionTimeoutChanged)
+                    .width(90),
+                text_input("Stop drain", &self.drain.content)
+                    .on_input(Message::DrainTimeoutChanged)
+                    .width(90),
+            ]
+            .spacing(5),
+            text(timeout_err).color(Color::from_rgb(255.0, 0.0, 0.0))
+        ]
+        .into()
+    }
+}
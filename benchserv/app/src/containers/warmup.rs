@@ -0,0 +1,53 @@
+use super::*;
+use crate::benchmark;
+use iced::widget::column;
+
+/// start of the test left out of the results, empty input means no warm-up
+#[derive(Default)]
+pub struct Warmup(pub TextInputState);
+
+impl Deref for Warmup {
+    type Target = TextInputState;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Warmup {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Warmup {
+    pub fn value(&self) -> Option<benchmark::Warmup> {
+        self.content.parse().ok()
+    }
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        use Message::*;
+
+        match message {
+            WarmupChanged(new_content) => {
+                self.incorrect =
+                    !new_content.is_empty() && new_content.parse::<benchmark::Warmup>().is_err();
+                self.content = new_content;
+
+                Task::none()
+            }
+            _ => Task::none(),
+        }
+    }
+    pub fn view(&self) -> Element<'_, Message> {
+        let warmup_err = if self.incorrect { "Bad warm-up" } else { "" };
+
+        column![
+            text!("Warm-up:"),
+            text_input("30s or 500 req", &self.content)
+                .on_input(Message::WarmupChanged)
+                .width(120),
+            text(warmup_err).color(Color::from_rgb(255.0, 0.0, 0.0))
+        ]
+        .into()
+    }
+}
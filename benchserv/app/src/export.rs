@@ -0,0 +1,637 @@
+use crate::{
+    benchmark::{Metric, Mode, Outcome},
+    stats::{Interval, Percentiles},
+};
+use serde::{Deserialize, Serialize, Serializer, ser::SerializeSeq};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tempfile::TempPath;
+
+/// numbers of a finished or running test saved for analysis outside the app,
+/// the warm-up is left out
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Export {
+    pub mode: Mode,
+    /// the target set in the inputs followed by the compared ones
+    pub targets: Vec<TargetResults>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TargetResults {
+    /// address and plugin of the target
+    pub label: String,
+    /// not read back, the baselines only need the intervals
+    #[serde(skip_deserializing)]
+    pub requests: Requests,
+    pub intervals: Vec<IntervalRow>,
+}
+
+/// requests of a run written to a temporary file as they finish, long tests
+/// at high rates would need too much memory otherwise
+#[derive(Debug)]
+pub struct RequestLog {
+    file: Arc<TempPath>,
+    writer: BufWriter<File>,
+    /// the first write that failed, the requests after it are dropped
+    error: Option<String>,
+}
+
+impl RequestLog {
+    pub fn new() -> io::Result<Self> {
+        // created under a random name, a file someone else made isn't opened
+        let (file, path) = tempfile::Builder::new()
+            .prefix("benchserv-requests-")
+            .suffix(".jsonl")
+            .tempfile()?
+            .into_parts();
+
+        Ok(RequestLog {
+            file: Arc::new(path),
+            writer: BufWriter::new(file),
+            error: None,
+        })
+    }
+    pub fn push(&mut self, row: &RequestRow) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let written = serde_json::to_writer(&mut self.writer, row)
+            .map_err(io::Error::from)
+            .and_then(|()| self.writer.write_all(b"\n"));
+        if let Err(err) = written {
+            self.error = Some(err.to_string());
+        }
+    }
+    /// requests written so far, the ones pushed later aren't part of it
+    pub fn snapshot(&mut self) -> Result<Requests, String> {
+        if let Some(err) = &self.error {
+            return Err(format!("can't write the requests: {err}"));
+        }
+
+        self.writer.flush().map_err(|err| err.to_string())?;
+        let len = self
+            .writer
+            .get_ref()
+            .metadata()
+            .map_err(|err| err.to_string())?
+            .len();
+
+        Ok(Requests {
+            file: Some(self.file.clone()),
+            len,
+        })
+    }
+}
+
+/// requests of a run read back from its log while they're exported
+#[derive(Clone, Debug, Default)]
+pub struct Requests {
+    /// removed once neither the run nor an export uses it
+    file: Option<Arc<TempPath>>,
+    /// bytes written when the snapshot was taken
+    len: u64,
+}
+
+impl Requests {
+    pub fn rows(&self) -> Result<impl Iterator<Item = Result<RequestRow, String>>, String> {
+        // no file for the results read back from json
+        let file = self.file.as_ref().map(|file| File::open(&**file));
+        let file = file.transpose().map_err(|err| err.to_string())?;
+        let len = self.len;
+
+        let lines = file
+            .into_iter()
+            .flat_map(move |file| BufReader::new(file.take(len)).lines());
+
+        Ok(lines.map(|line| {
+            let line = line.map_err(|err| err.to_string())?;
+            serde_json::from_str(&line).map_err(|err| err.to_string())
+        }))
+    }
+}
+
+impl Serialize for Requests {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        let mut seq = serializer.serialize_seq(None)?;
+        for row in self.rows().map_err(S::Error::custom)? {
+            seq.serialize_element(&row.map_err(S::Error::custom)?)?;
+        }
+
+        seq.end()
+    }
+}
+
+/// single request, latencies in milliseconds
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RequestRow {
+    /// start of the request in seconds since the unix epoch
+    pub timestamp: f64,
+    /// none for failed requests
+    pub latency_ms: Option<f64>,
+    /// number of the connection in the order they were spawned
+    pub connection: u64,
+    /// connections of the increase and profile modes, requests in flight in
+    /// the rate mode
+    pub step: Option<u64>,
+    /// category of the failure
+    pub error: Option<String>,
+    pub message: Option<String>,
+    pub phases: BTreeMap<String, f64>,
+    /// counters and gauges reported for the request
+    pub metrics: BTreeMap<String, f64>,
+}
+
+/// chart point, latencies in milliseconds
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IntervalRow {
+    /// second of the test or the connection number in the increase mode
+    pub index: u64,
+    pub duration_s: f64,
+    /// successful and failed requests
+    pub requests: u64,
+    pub errors: u64,
+    /// requests per second
+    pub throughput: f64,
+    // missing in files saved before the bands were charted
+    #[serde(default)]
+    pub p10_ms: f64,
+    #[serde(default)]
+    pub p25_ms: f64,
+    pub p50_ms: f64,
+    #[serde(default)]
+    pub p75_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub p999_ms: f64,
+    pub max_ms: f64,
+    /// counters per second and averages of the gauges
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl RequestRow {
+    /// `step` is sent by the test together with the outcome
+    pub fn new(outcome: &Outcome, step: Option<u64>) -> Self {
+        let (latency, phases, started, connection, metrics, error) = match outcome {
+            Outcome::Success(sample) => (
+                Some(sample.latency),
+                sample.phases.as_slice(),
+                sample.started,
+                sample.connection,
+                &sample.metrics,
+                None,
+            ),
+            Outcome::Failure(failure) => (
+                None,
+                [].as_slice(),
+                failure.started,
+                failure.connection,
+                &failure.metrics,
+                Some(failure),
+            ),
+        };
+
+        RequestRow {
+            timestamp: started
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            latency_ms: latency.map(millis),
+            connection,
+            step,
+            error: error.map(|failure| failure.kind.to_string()),
+            message: error.map(|failure| failure.message.clone()),
+            phases: phases
+                .iter()
+                .map(|(name, duration)| (name.clone(), millis(*duration)))
+                .collect(),
+            metrics: metrics
+                .iter()
+                .map(|(name, metric)| {
+                    let value = match *metric {
+                        Metric::Counter(count) => count as f64,
+                        Metric::Gauge(value) => value,
+                    };
+                    (name.clone(), value)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl IntervalRow {
+    pub fn new(index: u64, interval: &Interval) -> Self {
+        let Percentiles {
+            p10,
+            p25,
+            p50,
+            p75,
+            p90,
+            p99,
+            p999,
+            max,
+        } = interval.percentiles;
+        let nanos = |nanos: u64| millis(Duration::from_nanos(nanos));
+
+        IntervalRow {
+            index,
+            duration_s: interval.duration.as_secs_f64(),
+            requests: interval.requests,
+            errors: interval.errors.total(),
+            throughput: interval.throughput(),
+            p10_ms: nanos(p10),
+            p25_ms: nanos(p25),
+            p50_ms: nanos(p50),
+            p75_ms: nanos(p75),
+            p90_ms: nanos(p90),
+            p99_ms: nanos(p99),
+            p999_ms: nanos(p999),
+            max_ms: nanos(max),
+            metrics: interval
+                .metrics
+                .iter()
+                .filter_map(|(name, _)| Some((name.clone(), interval.metric(name)?)))
+                .collect(),
+        }
+    }
+}
+
+impl IntervalRow {
+    /// latencies of the point in nanoseconds, as charted
+    pub fn percentiles(&self) -> Percentiles {
+        let nanos = |millis: f64| (millis * 1_000_000.0) as u64;
+
+        Percentiles {
+            p10: nanos(self.p10_ms),
+            p25: nanos(self.p25_ms),
+            p50: nanos(self.p50_ms),
+            p75: nanos(self.p75_ms),
+            p90: nanos(self.p90_ms),
+            p99: nanos(self.p99_ms),
+            p999: nanos(self.p999_ms),
+            max: nanos(self.max_ms),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl Export {
+    /// results saved earlier as json, without the requests
+    pub fn read(path: &Path) -> Result<Export, String> {
+        let file = File::open(path).map_err(|err| err.to_string())?;
+
+        serde_json::from_reader(BufReader::new(file)).map_err(|err| err.to_string())
+    }
+    /// `results.csv` -> `results-intervals.csv`
+    pub fn intervals_file_name(path: &Path) -> String {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+        format!("{stem}-intervals.csv")
+    }
+    pub fn write_json(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|err| err.to_string())?;
+        let mut writer = BufWriter::new(file);
+
+        serde_json::to_writer(&mut writer, self).map_err(|err| err.to_string())?;
+        writer.flush().map_err(|err| err.to_string())
+    }
+    /// csv has a file for the requests and another one for the intervals
+    pub fn write_csv(&self, requests_path: &Path, intervals_path: &Path) -> Result<(), String> {
+        self.write_requests_csv(requests_path)?;
+        self.write_intervals_csv(intervals_path)
+    }
+    fn write_requests_csv(&self, path: &Path) -> Result<(), String> {
+        // the log is read twice, first for the column names
+        let mut phases = BTreeSet::new();
+        let mut metrics = BTreeSet::new();
+        for target in &self.targets {
+            for request in target.requests.rows()? {
+                let request = request?;
+                phases.extend(request.phases.into_keys());
+                metrics.extend(request.metrics.into_keys());
+            }
+        }
+        let phases: Vec<_> = phases.into_iter().collect();
+        let metrics: Vec<_> = metrics.into_iter().collect();
+
+        let header = [
+            "target",
+            "timestamp",
+            "latency_ms",
+            "connection",
+            "step",
+            "error",
+            "message",
+        ]
+        .map(str::to_owned);
+        let requests = self
+            .targets
+            .iter()
+            .map(|target| Ok((target, target.requests.rows()?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let rows = requests.into_iter().flat_map(|(target, requests)| {
+            requests.map(|request| {
+                let request = request?;
+                let row = [
+                    target.label.clone(),
+                    format!("{:.6}", request.timestamp),
+                    optional(request.latency_ms),
+                    request.connection.to_string(),
+                    optional(request.step),
+                    request.error.clone().unwrap_or_default(),
+                    request.message.clone().unwrap_or_default(),
+                ]
+                .into_iter()
+                .chain(columns(&phases, &request.phases))
+                .chain(columns(&metrics, &request.metrics));
+
+                Ok(row.collect::<Vec<_>>())
+            })
+        });
+
+        write_csv(path, header.into_iter(), &phases, &metrics, rows)
+    }
+    fn write_intervals_csv(&self, path: &Path) -> Result<(), String> {
+        let intervals = || self.targets.iter().flat_map(|target| &target.intervals);
+        let metrics = column_names(intervals().map(|interval| &interval.metrics));
+
+        let header = [
+            "target",
+            "index",
+            "duration_s",
+            "requests",
+            "errors",
+            "throughput",
+            "p10_ms",
+            "p25_ms",
+            "p50_ms",
+            "p75_ms",
+            "p90_ms",
+            "p99_ms",
+            "p999_ms",
+            "max_ms",
+        ]
+        .map(str::to_owned);
+        let rows = self.targets.iter().flat_map(|target| {
+            target.intervals.iter().map(|interval| {
+                [
+                    target.label.clone(),
+                    interval.index.to_string(),
+                    interval.duration_s.to_string(),
+                    interval.requests.to_string(),
+                    interval.errors.to_string(),
+                    interval.throughput.to_string(),
+                    interval.p10_ms.to_string(),
+                    interval.p25_ms.to_string(),
+                    interval.p50_ms.to_string(),
+                    interval.p75_ms.to_string(),
+                    interval.p90_ms.to_string(),
+                    interval.p99_ms.to_string(),
+                    interval.p999_ms.to_string(),
+                    interval.max_ms.to_string(),
+                ]
+                .into_iter()
+                .chain(columns(&metrics, &interval.metrics))
+                .collect()
+            })
+        });
+
+        write_csv(path, header.into_iter(), &[], &metrics, rows.map(Ok))
+    }
+}
+
+/// every name used by any of the rows, in alphabetical order
+fn column_names<'a>(maps: impl Iterator<Item = &'a BTreeMap<String, f64>>) -> Vec<String> {
+    let names: BTreeSet<_> = maps.flat_map(|map| map.keys()).collect();
+
+    names.into_iter().cloned().collect()
+}
+
+/// values of the named columns, empty when the row doesn't have it
+fn columns<'a>(
+    names: &'a [String],
+    values: &'a BTreeMap<String, f64>,
+) -> impl Iterator<Item = String> + 'a {
+    names.iter().map(|name| optional(values.get(name)))
+}
+
+fn optional(value: Option<impl ToString>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// phases and metrics get a column each after the fixed ones
+fn write_csv(
+    path: &Path,
+    header: impl Iterator<Item = String>,
+    phases: &[String],
+    metrics: &[String],
+    rows: impl Iterator<Item = Result<Vec<String>, String>>,
+) -> Result<(), String> {
+    let mut writer = csv::Writer::from_path(path).map_err(|err| err.to_string())?;
+
+    let header = header
+        .chain(phases.iter().map(|name| format!("phase_{name}_ms")))
+        .chain(metrics.iter().map(|name| format!("metric_{name}")));
+    writer.write_record(header).map_err(|err| err.to_string())?;
+
+    for row in rows {
+        writer.write_record(row?).map_err(|err| err.to_string())?;
+    }
+
+    writer.flush().map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        benchmark::Sample,
+        fixtures::{failure, interval, sample, temp_file},
+    };
+    use interface::ErrorKind;
+
+    fn success(connection: u64, phase: &str) -> Outcome {
+        Outcome::Success(Sample {
+            latency: Duration::from_millis(3),
+            phases: vec![(phase.to_owned(), Duration::from_millis(1))],
+            connection,
+            ..sample(3)
+        })
+    }
+
+    #[test]
+    fn request_log() {
+        let mut log = RequestLog::new().unwrap();
+        log.push(&RequestRow::new(&success(1, "connected"), Some(5)));
+        log.push(&RequestRow::new(
+            &Outcome::Failure(failure(ErrorKind::Timeout)),
+            None,
+        ));
+        let first = log.snapshot().unwrap();
+        log.push(&RequestRow::new(&success(3, "connected"), Some(6)));
+        let second = log.snapshot().unwrap();
+
+        let rows: Vec<_> = first.rows().unwrap().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].timestamp, 1.5);
+        assert_eq!(rows[0].latency_ms, Some(3.0));
+        assert_eq!(rows[0].step, Some(5));
+        assert_eq!(
+            rows[0].phases,
+            BTreeMap::from([("connected".to_owned(), 1.0)])
+        );
+        assert_eq!(
+            rows[0].metrics,
+            BTreeMap::from([("bytes".to_owned(), 100.0)])
+        );
+        assert_eq!(rows[1].latency_ms, None);
+        assert_eq!(rows[1].error.as_deref(), Some("timeout"));
+        assert_eq!(rows[1].message.as_deref(), Some("timed out, \"again\""));
+        assert_eq!(second.rows().unwrap().count(), 3);
+
+        // the file is kept for the exports still using it
+        let path = log.file.to_path_buf();
+        drop(log);
+        drop(first);
+        assert!(path.exists());
+        drop(second);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn csv() {
+        let mut first = RequestLog::new().unwrap();
+        first.push(&RequestRow::new(&success(1, "connected"), Some(5)));
+        first.push(&RequestRow::new(
+            &Outcome::Failure(failure(ErrorKind::Timeout)),
+            Some(5),
+        ));
+        let mut second = RequestLog::new().unwrap();
+        second.push(&RequestRow::new(&success(1, "written"), None));
+
+        let export = Export {
+            mode: Mode::Constant,
+            targets: vec![
+                TargetResults {
+                    label: "A".to_owned(),
+                    requests: first.snapshot().unwrap(),
+                    intervals: vec![IntervalRow::new(1, &interval(2))],
+                },
+                TargetResults {
+                    label: "B".to_owned(),
+                    requests: second.snapshot().unwrap(),
+                    intervals: Vec::new(),
+                },
+            ],
+        };
+        let requests = temp_file(".csv");
+        let intervals = temp_file("-intervals.csv");
+        export.write_csv(&requests, &intervals).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&requests).unwrap(),
+            "target,timestamp,latency_ms,connection,step,error,message,\
+             phase_connected_ms,phase_written_ms,metric_bytes,metric_pool\n\
+             A,1.500000,3,1,5,,,1,,100,\n\
+             A,2.000000,,2,5,timeout,\"timed out, \"\"again\"\"\",,,,2\n\
+             B,1.500000,3,1,,,,,1,100,\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&intervals).unwrap(),
+            "target,index,duration_s,requests,errors,throughput,p10_ms,p25_ms,\
+             p50_ms,p75_ms,p90_ms,p99_ms,p999_ms,max_ms,metric_bytes\n\
+             A,1,1,10,1,10,0,0,2,0,0,0,0,4,300\n"
+        );
+    }
+
+    #[test]
+    fn intervals_file_name() {
+        assert_eq!(
+            Export::intervals_file_name(Path::new("/tmp/results.csv")),
+            "results-intervals.csv"
+        );
+        assert_eq!(
+            Export::intervals_file_name(Path::new("results")),
+            "results-intervals.csv"
+        );
+    }
+
+    #[test]
+    fn json_is_read_without_requests() {
+        let mut log = RequestLog::new().unwrap();
+        log.push(&RequestRow::new(&success(1, "connected"), None));
+        let export = Export {
+            mode: Mode::Rate,
+            targets: vec![TargetResults {
+                label: "A".to_owned(),
+                requests: log.snapshot().unwrap(),
+                intervals: vec![IntervalRow::new(1, &interval(2))],
+            }],
+        };
+        let file = temp_file(".json");
+        export.write_json(&file).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(json["mode"], "rate");
+        assert_eq!(json["targets"][0]["requests"][0]["latency_ms"], 3.0);
+
+        let read = Export::read(&file).unwrap();
+        assert_eq!(read.mode, Mode::Rate);
+        assert_eq!(read.targets[0].label, "A");
+        assert_eq!(read.targets[0].requests.rows().unwrap().count(), 0);
+        assert_eq!(read.targets[0].intervals.len(), 1);
+    }
+
+    #[test]
+    fn json_without_bands_is_read() {
+        // saved before the p10, p25 and p75 bands were exported
+        let file = temp_file(".json");
+        std::fs::write(
+            &file,
+            r#"{"mode": "constant", "targets": [{"label": "A", "intervals": [{
+                "index": 1, "duration_s": 1.0, "requests": 10, "errors": 0, "throughput": 10.0,
+                "p50_ms": 2.0, "p90_ms": 3.0, "p99_ms": 4.0, "p999_ms": 4.0, "max_ms": 4.0,
+                "metrics": {}
+            }]}]}"#,
+        )
+        .unwrap();
+        let read = Export::read(&file).unwrap();
+
+        assert_eq!(read.mode, Mode::Constant);
+        assert_eq!(
+            read.targets[0].intervals[0].percentiles(),
+            Percentiles {
+                p50: 2_000_000,
+                p90: 3_000_000,
+                p99: 4_000_000,
+                p999: 4_000_000,
+                max: 4_000_000,
+                ..Percentiles::default()
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_mode_is_rejected() {
+        let file = temp_file(".json");
+        std::fs::write(&file, r#"{"mode": "sideways", "targets": []}"#).unwrap();
+
+        assert!(
+            Export::read(&file)
+                .unwrap_err()
+                .contains("unknown variant `sideways`")
+        );
+    }
+}
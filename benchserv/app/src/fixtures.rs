@@ -0,0 +1,60 @@
+use crate::{
+    benchmark::{Failure, Metric, Sample},
+    stats::{ErrorCounts, Interval, Percentiles},
+};
+use interface::ErrorKind;
+use std::time::{Duration, SystemTime};
+use tempfile::TempPath;
+
+/// successful request of the first connection, started 1.5s after the epoch
+pub fn sample(latency_ms: u64) -> Sample {
+    Sample {
+        latency: Duration::from_millis(latency_ms),
+        phases: vec![("connected".to_owned(), Duration::from_millis(1))],
+        metrics: vec![("bytes".to_owned(), Metric::Counter(100))],
+        warmup: false,
+        connection: 1,
+        started: SystemTime::UNIX_EPOCH + Duration::from_millis(1500),
+    }
+}
+
+/// failed request of the second connection, started 2s after the epoch, the
+/// message needs quoting in csv
+pub fn failure(kind: ErrorKind) -> Failure {
+    Failure {
+        kind,
+        message: "timed out, \"again\"".to_owned(),
+        metrics: vec![("pool".to_owned(), Metric::Gauge(2.0))],
+        warmup: false,
+        connection: 2,
+        started: SystemTime::UNIX_EPOCH + Duration::from_secs(2),
+    }
+}
+
+/// second with 10 requests, one of them failed, the max is twice the median
+pub fn interval(p50_ms: u64) -> Interval {
+    let mut errors = ErrorCounts::default();
+    errors.record(ErrorKind::Reset);
+
+    Interval {
+        percentiles: Percentiles {
+            p50: p50_ms * 1_000_000,
+            max: p50_ms * 2_000_000,
+            ..Percentiles::default()
+        },
+        requests: 10,
+        errors,
+        duration: Duration::from_secs(1),
+        metrics: vec![("bytes".to_owned(), Metric::Counter(300))],
+        ..Interval::default()
+    }
+}
+
+/// removed when dropped
+pub fn temp_file(suffix: &str) -> TempPath {
+    tempfile::Builder::new()
+        .suffix(suffix)
+        .tempfile()
+        .unwrap()
+        .into_temp_path()
+}
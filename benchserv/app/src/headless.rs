@@ -0,0 +1,495 @@
+use crate::{
+    benchmark::{
+        self, ConnectionManager, Limits, Metric, Mode, Outcome, Parameters, TestConfig, TestHandle,
+        Timeouts, Warmup, Wrapper,
+    },
+    plugins::{self, LoadError, PluginInfo},
+    profile::{self, Profile},
+    stats::{self, Interval, IntervalStats},
+    target::{self, Target},
+};
+use clap::Parser;
+use futures::StreamExt;
+use interface::Parameter;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::mpsc::UnboundedSender;
+
+#[derive(Parser, Debug)]
+#[command(about = "Server agnostic application for testing servers latency")]
+pub struct Args {
+    /// Run the test in the terminal instead of opening the window
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Address or host name of the tested server, e.g. 127.0.0.1:80 or example.com:80
+    #[arg(long, required_if_eq("headless", "true"), value_parser = target::parse)]
+    pub target: Option<Target>,
+
+    /// Rotate connections through all addresses of the host name instead of
+    /// using the first one
+    #[arg(long)]
+    pub rotate_addresses: bool,
+
+    /// Name of the plugin in the plugins directory
+    #[arg(long, required_if_eq("headless", "true"))]
+    pub plugin: Option<String>,
+
+    /// Test mode
+    #[arg(long, value_enum, default_value_t = Mode::Constant)]
+    pub mode: Mode,
+
+    /// Connections number in constant mode, max connections in increase mode
+    #[arg(long, default_value_t = 100)]
+    pub connections: u64,
+
+    /// Requests started every second in rate mode
+    #[arg(long, required_if_eq("mode", "rate"),
+          value_parser = clap::value_parser!(u64).range(1..))]
+    pub rate: Option<u64>,
+
+    /// File with the load profile stages in profile mode, e.g.
+    /// `ramp 500 60s; hold 2m; spike 2000 10s; ramp 0 30s`
+    #[arg(long, required_if_eq("mode", "profile"), value_parser = read_profile)]
+    pub profile: Option<Profile>,
+
+    /// Significant figures kept by the latency histograms (1-5)
+    #[arg(long, default_value_t = stats::DEFAULT_PRECISION,
+          value_parser = clap::value_parser!(u8).range(1..=stats::MAX_PRECISION as i64))]
+    pub precision: u8,
+
+    /// Cancel requests running longer than this many milliseconds
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub request_timeout: Option<u64>,
+
+    /// Cancel connections running longer than this many milliseconds
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub connection_timeout: Option<u64>,
+
+    /// Let requests in progress end for this many milliseconds after the test
+    /// stops or reaches a limit, they're aborted right away by default
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub drain_timeout: Option<u64>,
+
+    /// Start of the test left out of the results, a duration or a number of
+    /// requests, e.g. 30s or "500 req"
+    #[arg(long)]
+    pub warmup: Option<Warmup>,
+
+    /// End the constant or rate mode after this long, e.g. 60s or 2m,
+    /// counted after the warm-up
+    #[arg(long, value_parser = profile::parse_duration)]
+    pub duration: Option<Duration>,
+
+    /// End the constant or rate mode after this many finished requests,
+    /// counted after the warm-up
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub requests: Option<u64>,
+
+    /// Plugin parameter, can be repeated, e.g. --param payload_size=4096
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_parameter)]
+    pub parameters: Vec<(String, String)>,
+
+    /// Directory with compiled plugins
+    #[arg(long, env = "PLUGINS_PATH", default_value = "plugins")]
+    pub plugins_path: String,
+}
+
+fn parse_parameter(parameter: &str) -> Result<(String, String), String> {
+    let (name, value) = parameter
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got `{parameter}`"))?;
+
+    Ok((name.to_owned(), value.to_owned()))
+}
+
+/// defaults of the parameters declared by the plugin overridden by the ones
+/// given in the arguments
+fn plugin_parameters(
+    schema: &[Parameter],
+    overrides: Vec<(String, String)>,
+) -> Result<Parameters, String> {
+    let mut parameters: Parameters = schema
+        .iter()
+        .map(|parameter| (parameter.name.to_string(), parameter.default.to_string()))
+        .collect();
+
+    for (name, value) in overrides {
+        let Some(parameter) = schema
+            .iter()
+            .find(|parameter| parameter.name == name.as_str())
+        else {
+            return Err(format!("plugin has no parameter `{name}`"));
+        };
+        if !parameter.kind.is_valid(&value) {
+            return Err(format!("bad value `{value}` of parameter `{name}`"));
+        }
+
+        parameters.insert(name, value);
+    }
+
+    Ok(parameters)
+}
+
+fn read_profile(path: &str) -> Result<Profile, String> {
+    let content = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+
+    content.parse()
+}
+
+/// runs the test without the gui, printing results to stdout until the test
+/// ends or ctrl-c is pressed
+pub fn run(args: Args) {
+    let runtime = tokio::runtime::Runtime::new().expect("Unable to start async runtime");
+
+    // both are required by clap in headless mode
+    let plugin_name = args.plugin.unwrap();
+    let mut target = args.target.unwrap();
+
+    let plugin = PluginInfo::load(&args.plugins_path, &plugin_name);
+    if let Some(err) = &plugin.error {
+        eprintln!("error: plugin `{plugin_name}` {}", err.summary);
+        eprintln!("{}", err.details);
+        exit(2);
+    }
+
+    target.addresses.retain(|address| plugin.supports(address));
+    if target.addresses.is_empty() {
+        eprintln!(
+            "error: plugin `{plugin_name}` supports only {} addresses",
+            plugin.metadata.families
+        );
+        exit(2);
+    }
+    if !args.rotate_addresses {
+        target.addresses.truncate(1);
+    }
+    if target.host.is_some() {
+        eprintln!("target: {target}");
+    }
+
+    let parameters = match plugin_parameters(&plugin.parameters, args.parameters) {
+        Ok(parameters) => parameters,
+        Err(err) => {
+            eprintln!("error: {err}");
+            exit(2);
+        }
+    };
+
+    let config = TestConfig {
+        target,
+        plugin_name,
+        plugins_path: args.plugins_path,
+        timeouts: Timeouts {
+            request: args.request_timeout.map(Duration::from_millis),
+            connection: args.connection_timeout.map(Duration::from_millis),
+            drain: args.drain_timeout.map(Duration::from_millis),
+        },
+        parameters: Arc::new(parameters),
+        warmup: args.warmup,
+        limits: Limits {
+            duration: args.duration,
+            requests: args.requests,
+        },
+    };
+    let interval = IntervalStats::new(args.precision);
+
+    runtime.block_on(async move {
+        match args.mode {
+            Mode::Constant => run_constant(args.connections, config, interval).await,
+            Mode::Increase => run_increase(args.connections, config, interval).await,
+            // required by clap in rate mode
+            Mode::Rate => run_rate(args.rate.unwrap(), config, interval).await,
+            Mode::Profile => run_profile(args.profile.unwrap(), config, interval).await,
+        }
+    });
+}
+
+/// the copies of the plugins aren't removed by `process::exit` on its own
+fn exit(code: i32) -> ! {
+    plugins::remove_copies();
+    std::process::exit(code)
+}
+
+/// exits when the plugin couldn't be loaded
+fn started<T>(data: Result<TestHandle<T>, LoadError>) -> TestHandle<T> {
+    data.unwrap_or_else(|err| {
+        eprintln!("error: {}", err.summary);
+        eprintln!("{}", err.details);
+        exit(2);
+    })
+}
+
+async fn run_constant(connections_number: u64, config: TestConfig, mut interval: IntervalStats) {
+    let data = ConnectionManager::run_test_constant(connections_number, config).await;
+    let (mut wrapper, sender) = Arc::into_inner(started(data)).unwrap();
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    // first tick completes immediately
+    ticker.tick().await;
+
+    let mut seconds = 0;
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    print_header("time", None);
+    loop {
+        tokio::select! {
+            Some(outcome) = wrapper.next() => {
+                // limit reached
+                if outcome == Outcome::FINISHED {
+                    break;
+                }
+
+                interval.record(&outcome);
+            }
+            _ = ticker.tick() => {
+                seconds += 1;
+
+                print_row(&format!("{seconds}s"), &interval.finish(), None);
+            }
+            _ = &mut ctrl_c => break,
+        }
+    }
+
+    stop(wrapper, sender, |outcome| {
+        record_queued(&mut interval, outcome)
+    })
+    .await;
+    // last partial second
+    print_row(&format!("{}s", seconds + 1), &interval.finish(), None);
+    print_summary(&interval);
+}
+
+async fn run_increase(max_connections: u64, config: TestConfig, mut interval: IntervalStats) {
+    let data = ConnectionManager::run_test_increase(max_connections, config).await;
+    let (mut wrapper, sender) = Arc::into_inner(started(data)).unwrap();
+
+    let mut current_connections = 1;
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    print_header("connections", None);
+    let finished = loop {
+        let (outcome, connections) = tokio::select! {
+            Some(value) = wrapper.next() => value,
+            _ = &mut ctrl_c => break false,
+            else => break false,
+        };
+
+        if connections != current_connections {
+            print_row(&current_connections.to_string(), &interval.finish(), None);
+
+            current_connections = connections;
+        }
+
+        // test reached max connections
+        if outcome == Outcome::FINISHED {
+            break true;
+        }
+
+        interval.record(&outcome);
+    };
+
+    stop(wrapper, sender, |(outcome, _)| {
+        record_queued(&mut interval, outcome)
+    })
+    .await;
+    // the step in progress when the test was stopped
+    if !finished {
+        print_row(&current_connections.to_string(), &interval.finish(), None);
+    }
+    print_summary(&interval);
+}
+
+async fn run_rate(rate: u64, config: TestConfig, mut interval: IntervalStats) {
+    let data = ConnectionManager::run_test_rate(rate, config).await;
+    let (mut wrapper, sender) = Arc::into_inner(started(data)).unwrap();
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    // first tick completes immediately
+    ticker.tick().await;
+
+    let mut seconds = 0;
+    let mut max_in_flight = 0;
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    print_header("time", Some("in flight"));
+    loop {
+        tokio::select! {
+            Some((outcome, in_flight)) = wrapper.next() => {
+                if outcome == Outcome::FINISHED {
+                    break;
+                }
+
+                interval.record(&outcome);
+                max_in_flight = max_in_flight.max(in_flight);
+            }
+            _ = ticker.tick() => {
+                seconds += 1;
+
+                print_row(&format!("{seconds}s"), &interval.finish(), Some(max_in_flight));
+                max_in_flight = 0;
+            }
+            _ = &mut ctrl_c => break,
+        }
+    }
+
+    stop(wrapper, sender, |(outcome, in_flight)| {
+        record_queued(&mut interval, outcome);
+        max_in_flight = max_in_flight.max(in_flight);
+    })
+    .await;
+    // last partial second
+    print_row(
+        &format!("{}s", seconds + 1),
+        &interval.finish(),
+        Some(max_in_flight),
+    );
+    print_summary(&interval);
+}
+
+async fn run_profile(profile: Profile, config: TestConfig, mut interval: IntervalStats) {
+    let data = ConnectionManager::run_test_profile(profile, config).await;
+    let (mut wrapper, sender) = Arc::into_inner(started(data)).unwrap();
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    // first tick completes immediately
+    ticker.tick().await;
+
+    let mut seconds = 0;
+    let mut connections = 0;
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    print_header("time", Some("connections"));
+    loop {
+        tokio::select! {
+            Some((outcome, current)) = wrapper.next() => {
+                connections = current;
+
+                // profile is over
+                if outcome == Outcome::FINISHED {
+                    break;
+                }
+
+                interval.record(&outcome);
+            }
+            _ = ticker.tick() => {
+                seconds += 1;
+
+                print_row(&format!("{seconds}s"), &interval.finish(), Some(connections));
+            }
+            _ = &mut ctrl_c => break,
+        }
+    }
+
+    stop(wrapper, sender, |(outcome, _)| {
+        record_queued(&mut interval, outcome)
+    })
+    .await;
+    // last partial second
+    print_row(
+        &format!("{}s", seconds + 1),
+        &interval.finish(),
+        Some(connections),
+    );
+    print_summary(&interval);
+}
+
+/// stops the test and waits until its connections are aborted and the plugin
+/// is told the test ended, results sent before the stop are passed to `record`
+async fn stop<T>(
+    mut wrapper: Wrapper<T>,
+    sender: UnboundedSender<benchmark::Message>,
+    mut record: impl FnMut(T),
+) {
+    let _ = sender.send(benchmark::Message::Stop);
+
+    while let Some(value) = wrapper.next().await {
+        record(value);
+    }
+}
+
+/// result left in the stream when the test is stopped, the end of the test
+/// can be queued there too when it ended right before
+fn record_queued(interval: &mut IntervalStats, outcome: Outcome) {
+    if outcome != Outcome::FINISHED {
+        interval.record(&outcome);
+    }
+}
+
+/// whole test without the warm-up, printed once it ends
+fn print_summary(interval: &IntervalStats) {
+    println!();
+    print_row("total", &interval.summary(), None);
+}
+
+fn print_header(interval: &str, extra: Option<&str>) {
+    print!(
+        "{:>11} {:>9} {:>9} {:>9} {:>9} {:>9} {:>10} {:>9} {:>16}",
+        interval, "p50", "p90", "p99", "p99.9", "max", "requests", "req/s", "errors"
+    );
+    match extra {
+        Some(extra) => println!(" {extra:>10}"),
+        None => println!(),
+    }
+}
+
+fn print_row(label: &str, interval: &Interval, extra: Option<u64>) {
+    let percentiles = &interval.percentiles;
+    let errors = format!(
+        "{} ({:.2}%)",
+        interval.errors.total(),
+        interval.error_rate()
+    );
+
+    print!(
+        "{:>11} {:>9} {:>9} {:>9} {:>9} {:>9} {:>10} {:>9.0} {:>16}",
+        label,
+        stats::format_latency(percentiles.p50),
+        stats::format_latency(percentiles.p90),
+        stats::format_latency(percentiles.p99),
+        stats::format_latency(percentiles.p999),
+        stats::format_latency(percentiles.max),
+        interval.requests,
+        interval.throughput(),
+        errors
+    );
+    if let Some(extra) = extra {
+        print!(" {extra:>10}");
+    }
+    if interval.warmup {
+        print!(" warm-up");
+    }
+    println!();
+
+    // phases marked by the plugin under the whole request
+    for (name, percentiles) in &interval.phases {
+        println!(
+            "{:>11} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            name,
+            stats::format_latency(percentiles.p50),
+            stats::format_latency(percentiles.p90),
+            stats::format_latency(percentiles.p99),
+            stats::format_latency(percentiles.p999),
+            stats::format_latency(percentiles.max),
+        );
+    }
+
+    // counters per second and averaged gauges reported by the plugin
+    let metrics: Vec<_> = interval
+        .metrics
+        .iter()
+        .filter_map(|(name, metric)| {
+            let value = interval.metric(name)?;
+            match metric {
+                Metric::Counter(_) => Some(format!("{name}: {value:.0}/s")),
+                Metric::Gauge(_) => Some(format!("{name}: {value:.2}")),
+            }
+        })
+        .collect();
+    if !metrics.is_empty() {
+        println!("{:>11} {}", "metrics", metrics.join(", "));
+    }
+}
@@ -1,62 +1,70 @@
-use benchmark::ConnectionManager;
-use containers::ConnectionChart;
+use benchmark::{ConnectionManager, Outcome, TestConfig};
+use clap::Parser;
+use containers::{ConnectionChart, ImageOptions, Variant};
 use iced::{
-    Element, Length, Task,
-    widget::{column, container, row},
+    Color, Element, Length, Subscription, Task,
+    widget::{column, container, row, text},
 };
 use plotters::prelude::*;
 use plotters_iced::ChartBuilder;
+use plugins::LoadError;
 use rfd::{AsyncFileDialog, FileHandle};
-use std::{net::SocketAddr, str::FromStr, sync::Arc, time::Duration};
+use stats::{Interval, IntervalStats, Statistic};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+use target::Target;
 use tokio::sync::mpsc::UnboundedSender;
 
 mod benchmark;
 mod containers;
+mod export;
+#[cfg(test)]
+mod fixtures;
+mod headless;
+mod plugins;
+mod profile;
+mod stats;
+mod target;
 
 fn main() -> iced::Result {
-    iced::application("Benchserv", Main::update, Main::view)
-        .subscription(|_state| iced::time::every(Duration::from_secs(1)).map(|_| Message::Tick))
+    let args = headless::Args::parse();
+    if args.headless {
+        headless::run(args);
+        plugins::remove_copies();
+        return Ok(());
+    }
+
+    let result = iced::application("Benchserv", Main::update, Main::view)
+        .subscription(Main::subscription)
         .run_with(|| {
             let mut state = Main::default();
-            let plugins_path = std::env::var("PLUGINS_PATH").unwrap_or(String::from("plugins"));
-            let plugin_names = load_plugins_names(&plugins_path);
-
-            state.plugin_container.selected = plugin_names[0].clone();
-            state.plugin_container.all = plugin_names;
-            state.plugins_path = plugins_path;
+            state.load_plugins(args.plugins_path);
 
             (state, Task::none())
-        })
-}
-
-fn load_plugins_names(path: &str) -> Vec<String> {
-    let dir = std::fs::read_dir(path).expect("Specified path for plugins doesn't exist");
-
-    let mut names = vec![];
-    for entry in dir {
-        let Ok(entry) = entry else { continue };
-        let Ok(file_type) = entry.file_type() else {
-            continue;
-        };
-
-        if !file_type.is_file() {
-            continue;
-        }
-
-        let Ok(name) = entry.file_name().into_string() else {
-            continue;
-        };
+        });
+    plugins::remove_copies();
 
-        if !name.ends_with(".dll") && !name.ends_with(".so") {
-            continue;
-        }
+    result
+}
 
-        let name = name.split(".").next().unwrap();
+/// draws the saved chart on the image of any format, the file is written
+/// by `present`
+fn draw_image<DB: DrawingBackend>(
+    root: DrawingArea<DB, plotters::coord::Shift>,
+    chart: &ConnectionChart,
+    options: &ImageOptions,
+) -> Result<(), String> {
+    root.fill(&options.theme.background())
+        .map_err(|err| err.to_string())?;
 
-        names.push(name.to_owned());
-    }
+    let chart_builder = ChartBuilder::on(&root);
+    chart.build_chart_png(chart_builder, options);
 
-    names
+    root.present().map_err(|err| err.to_string())
 }
 
 #[derive(Clone, Debug)]
@@ -64,18 +72,61 @@ enum Message {
     // ip address text inputs
     IpChanged(String),
     PortChanged(String),
+    /// sent a moment after the host name was typed
+    ResolveHost(String),
+    HostResolved(String, Result<Vec<IpAddr>, String>),
+    AddressChoiceChanged(containers::AddressChoice),
 
     // plugin choosing
     PluginChange(String),
+    ParameterChanged(usize, String),
+    OpenPluginsFolder,
+    PluginsFolderOpened(Option<String>),
+    RescanPlugins,
+    WatchPluginsChanged(bool),
+    /// libraries in the watched directory changed
+    PluginsChanged(Result<(), String>),
 
     // choosing mode
     ConstantModeChanged(bool),
     IncreaseModeChanged(bool),
+    RateModeChanged(bool),
+    ProfileModeChanged(bool),
 
     // choosing connections number
     ConnectionsChangedSlider(f64),
     ConnectionsChangedInput(String),
 
+    // load profile
+    ProfileChanged(String),
+    OpenProfile,
+    ProfileOpened(Option<String>),
+
+    // histogram significant figures
+    PrecisionChanged(String),
+
+    // timeouts text inputs
+    RequestTimeoutChanged(String),
+    ConnectionTimeoutChanged(String),
+    DrainTimeoutChanged(String),
+
+    // requests left out of the results
+    WarmupChanged(String),
+
+    // end of the constant and rate modes
+    DurationLimitChanged(String),
+    RequestsLimitChanged(String),
+
+    // size and theme of the saved chart
+    ImageWidthChanged(String),
+    ImageHeightChanged(String),
+    ImageDpiChanged(String),
+    LightThemeChanged(bool),
+
+    // targets of the A/B test
+    CompareAdd,
+    CompareRemove(usize),
+
     // buttons
     RunTest,
     Pause,
@@ -83,22 +134,89 @@ enum Message {
     Stop,
     Save,
 
-    FileDialog(Option<FileHandle>, ConnectionChart),
-
-    // test data
-    ConstantTestInitData(Arc<(benchmark::Wrapper<u64>, UnboundedSender<benchmark::Message>)>),
+    // the chart is boxed to keep the message small
+    FileDialog(Option<FileHandle>, Box<ConnectionChart>),
+    /// file for the chart points of the requests saved as csv
+    IntervalsFileDialog(PathBuf, Option<FileHandle>),
+    Exported(Result<(), String>),
+    ImageSaved(Result<(), String>),
+    OpenBaseline,
+    BaselinesLoaded(Result<Vec<containers::Baseline>, String>),
+    ClearBaselines,
+
+    // test data, tagged with the index of the target
+    ConstantTestInitData(usize, Result<benchmark::TestHandle<Outcome>, LoadError>),
     IncreaseTestInitData(
-        Arc<(
-            benchmark::Wrapper<(u64, u64)>,
-            UnboundedSender<benchmark::Message>,
-        )>,
+        usize,
+        Result<benchmark::TestHandle<(Outcome, u64)>, LoadError>,
+    ),
+    RateTestInitData(
+        usize,
+        Result<benchmark::TestHandle<(Outcome, u64)>, LoadError>,
+    ),
+    ProfileTestInitData(
+        usize,
+        Result<benchmark::TestHandle<(Outcome, u64)>, LoadError>,
     ),
 
     // chart
     Tick,
-    ConstantNewData(u64),
-    IncreaseNewData((u64, u64)),
-    NewChartData(u64),
+    ConstantNewData(usize, Outcome),
+    IncreaseNewData(usize, (Outcome, u64)),
+    RateNewData(usize, (Outcome, u64)),
+    ProfileNewData(usize, (Outcome, u64)),
+    NewChartData(usize, Interval),
+    StatisticChanged(Statistic),
+    BandsToggled(bool),
+    P99LineToggled(bool),
+    StackPhasesChanged(bool),
+    PhaseToggled(String, bool),
+    SecondarySeriesChanged(containers::SecondarySeries),
+}
+
+/// test of one target, compared targets are tested at the same time
+struct Run {
+    label: String,
+    plugin_name: String,
+    sender: Option<UnboundedSender<benchmark::Message>>,
+    interval: IntervalStats,
+    increase_step: u64,
+    /// the test of the target ended on its own
+    finished: bool,
+    /// kept for the export, without the warm-up
+    requests: Result<export::RequestLog, String>,
+    /// every charted point, also the ones scrolled off the chart
+    intervals: Vec<Interval>,
+}
+
+impl Run {
+    fn new(label: String, plugin_name: String, precision: u8) -> Self {
+        Run {
+            label,
+            plugin_name,
+            sender: None,
+            interval: IntervalStats::new(precision),
+            increase_step: 1,
+            finished: false,
+            requests: export::RequestLog::new()
+                .map_err(|err| format!("can't create the requests file: {err}")),
+            intervals: Vec::new(),
+        }
+    }
+    /// requests and points of the run, the warm-up is left out
+    fn export(&mut self) -> Result<export::TargetResults, String> {
+        let requests = self.requests.as_mut().map_err(|err| err.clone())?;
+
+        Ok(export::TargetResults {
+            label: self.label.clone(),
+            requests: requests.snapshot()?,
+            intervals: (1..)
+                .zip(&self.intervals)
+                .filter(|(_, interval)| !interval.warmup)
+                .map(|(index, interval)| export::IntervalRow::new(index, interval))
+                .collect(),
+        })
+    }
 }
 
 #[derive(Default)]
@@ -110,35 +228,262 @@ struct Main {
     buttons_container: containers::Buttons,
     mode_container: containers::Mode,
     plugin_container: containers::PluginsSelect,
+    parameters_container: containers::PluginParameters,
+    precision_container: containers::Precision,
+    timeouts_container: containers::Timeouts,
+    warmup_container: containers::Warmup,
+    limits_container: containers::Limits,
+    profile_container: containers::Profile,
+    compare_container: containers::Compare,
+    image_container: containers::Image,
 
     // test conditions
     test_running: bool,
     paused: bool,
-    cant_run: bool,
+    /// reason the last run was refused
+    cant_run: Option<&'static str>,
 
     // benchmark
-    sender: Option<UnboundedSender<benchmark::Message>>,
+    /// the target set in the inputs followed by the compared ones
+    runs: Vec<Run>,
     plugins_path: String,
-
-    // chart data
-    data_points_constant: Vec<u64>,
-    data_points_increase: Vec<(u64, u64)>,
+    /// problem with the plugins shown above everything else
+    error: Option<String>,
+    /// the watched directory changed during the test
+    rescan_pending: bool,
 
     chart: ConnectionChart,
 }
 
 impl Main {
-    fn update(&mut self, message: Message) -> Task<Message> {
-        macro_rules! send_msg {
-            ($msg:expr) => {
-                self.sender.as_mut().unwrap().send($msg).unwrap();
-            };
+    /// scans the plugins directory, problems are shown in the banner
+    fn load_plugins(&mut self, plugins_path: String) {
+        let plugins = match plugins::scan(&plugins_path) {
+            Ok(plugins) => {
+                self.error = plugins
+                    .is_empty()
+                    .then(|| format!("No plugins found in `{plugins_path}`"));
+                plugins
+            }
+            Err(err) => {
+                self.error = Some(format!(
+                    "Can't read the plugins directory `{plugins_path}`: {err}"
+                ));
+                Vec::new()
+            }
+        };
+
+        let previous = self.plugin_container.selected.clone();
+        self.plugin_container.set_plugins(plugins);
+        self.plugins_path = plugins_path;
+        self.rescan_pending = false;
+
+        // rescanning keeps the values typed for the same plugin
+        self.load_parameters(previous == self.plugin_container.selected);
+    }
+    fn subscription(&self) -> Subscription<Message> {
+        let tick = iced::time::every(Duration::from_secs(1)).map(|_| Message::Tick);
+
+        if !self.plugin_container.watch {
+            return tick;
+        }
+
+        // a new folder restarts the watcher
+        let path = self.plugins_path.clone();
+        let watch = Subscription::run_with_id(path.clone(), plugins::watch(path))
+            .map(Message::PluginsChanged);
+
+        Subscription::batch([tick, watch])
+    }
+    /// fills the parameters form of the selected plugin
+    fn load_parameters(&mut self, keep_values: bool) {
+        let schema = self
+            .plugin_container
+            .selected()
+            .map(|plugin| plugin.parameters.clone())
+            .unwrap_or_default();
+
+        if keep_values {
+            self.parameters_container.update_schema(schema);
+        } else {
+            self.parameters_container.set_schema(schema);
+        }
+    }
+    /// charts the current interval, totals come from the single requests so
+    /// the warm-up is left out exactly
+    fn push_interval(&mut self, target: usize) {
+        let Some(run) = self.runs.get_mut(target) else {
+            return;
+        };
+        let interval = run.interval.finish();
+        let total = run.interval.summary();
+        run.intervals.push(interval.clone());
+
+        ConnectionChart::update(&mut self.chart, Message::NewChartData(target, interval));
+        if let Some(series) = self.chart.series_mut(target) {
+            series.total = total;
+        }
+    }
+    /// writes json, or csv when the path for the chart points is given
+    fn export_results(&mut self, path: PathBuf, intervals_path: Option<PathBuf>) -> Task<Message> {
+        let targets: Result<Vec<_>, _> = self.runs.iter_mut().map(Run::export).collect();
+        let targets = match targets {
+            Ok(targets) => targets,
+            Err(err) => {
+                self.error = Some(format!("Can't export the results: {err}"));
+                return Task::none();
+            }
+        };
+        let results = export::Export {
+            mode: self.mode_container.value(),
+            targets,
+        };
+
+        // big runs take a while to write
+        Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || match intervals_path {
+                    Some(intervals_path) => results.write_csv(&path, &intervals_path),
+                    None => results.write_json(&path),
+                })
+                .await
+                .unwrap()
+            },
+            Message::Exported,
+        )
+    }
+    /// `step` is the number sent by the test together with the outcome
+    fn record(&mut self, target: usize, outcome: Outcome, step: Option<u64>) {
+        let Some(run) = self.runs.get_mut(target) else {
+            return;
+        };
+
+        run.interval.record(&outcome);
+        if !outcome.warmup()
+            && let Ok(requests) = &mut run.requests
+        {
+            requests.push(&export::RequestRow::new(&outcome, step));
+        }
+    }
+    /// the test of the target ended on its own, the summary is shown once all
+    /// targets are done
+    fn finish_run(&mut self, target: usize) {
+        if let Some(run) = self.runs.get_mut(target) {
+            run.finished = true;
+            run.sender = None;
+        }
+
+        if self.runs.iter().all(|run| run.finished) {
+            self.chart.finished = true;
+            self.paused = false;
+            self.test_running = false;
+        }
+    }
+    /// keeps the sender of the started test, false when the test was stopped
+    /// before it started and was told to stop right away
+    fn start_run(&mut self, target: usize, sender: UnboundedSender<benchmark::Message>) -> bool {
+        let Some(run) = self.runs.get_mut(target).filter(|_| self.test_running) else {
+            let _ = sender.send(benchmark::Message::Stop);
+            return false;
+        };
+
+        // paused before this target started, its interval already is
+        if self.paused {
+            let _ = sender.send(benchmark::Message::Pause);
+        }
+        run.sender = Some(sender);
+
+        true
+    }
+    /// sends the message to the tests of all targets still running, a test
+    /// that ended on its own drops the receiver before its end is handled
+    fn send_all(&self, message: fn() -> benchmark::Message) {
+        for sender in self.runs.iter().filter_map(|run| run.sender.as_ref()) {
+            let _ = sender.send(message());
+        }
+    }
+    /// target set in the inputs, the error says what's missing
+    fn current_variant(&self) -> Result<Variant, &'static str> {
+        let containers = [&self.ip_container.input, &self.port_container.0];
+
+        let all_correct = containers.iter().map(|c| c.incorrect).all(|k| !k);
+        let all_not_empty = containers.iter().map(|c| &c.content).all(|s| !s.is_empty());
+
+        if !all_correct || !all_not_empty || self.parameters_container.incorrect() {
+            return Err("Fill all inputs before running!");
         }
 
+        let Some(plugin) = self.plugin_container.selected() else {
+            return Err("Select a plugin that can be loaded!");
+        };
+        let Some(addresses) = self.ip_container.chosen_addresses() else {
+            return Err("Host name isn't resolved!");
+        };
+
+        // checked with the other inputs
+        let port: u16 = self.port_container.content.parse().unwrap();
+        let addresses: Vec<_> = addresses
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .filter(|address| plugin.supports(address))
+            .collect();
+
+        if addresses.is_empty() {
+            return Err("Selected plugin doesn't support this address family!");
+        }
+
+        let host = self
+            .ip_container
+            .is_hostname()
+            .then_some(self.ip_container.content.as_str());
+
+        Ok(Variant {
+            target: Target::new(host, addresses),
+            plugin_name: self.plugin_container.selected.clone(),
+            parameters: Arc::new(self.parameters_container.value()),
+        })
+    }
+    /// starts the test of one target in the chosen mode
+    fn run_test(&self, target: usize, config: TestConfig) -> Task<Message> {
+        if self.mode_container.constant {
+            Task::perform(
+                ConnectionManager::run_test_constant(
+                    self.connections_container.value as u64,
+                    config,
+                ),
+                move |data| Message::ConstantTestInitData(target, data),
+            )
+        } else if self.mode_container.increase {
+            Task::perform(
+                ConnectionManager::run_test_increase(
+                    self.connections_container.value as u64,
+                    config,
+                ),
+                move |data| Message::IncreaseTestInitData(target, data),
+            )
+        } else if self.mode_container.profile {
+            // checked before running
+            let profile = self.profile_container.value.clone().unwrap();
+
+            Task::perform(
+                ConnectionManager::run_test_profile(profile, config),
+                move |data| Message::ProfileTestInitData(target, data),
+            )
+        } else {
+            Task::perform(
+                ConnectionManager::run_test_rate(self.connections_container.value as u64, config),
+                move |data| Message::RateTestInitData(target, data),
+            )
+        }
+    }
+    fn update(&mut self, message: Message) -> Task<Message> {
         use Message::*;
 
         match message {
-            IpChanged(_) if !self.test_running => self.ip_container.update(message),
+            IpChanged(_) | AddressChoiceChanged(_) if !self.test_running => {
+                self.ip_container.update(message)
+            }
+            ResolveHost(_) | HostResolved(..) => self.ip_container.update(message),
             PortChanged(_) if !self.test_running => self.port_container.update(message),
             ConnectionsChangedSlider(val) if !self.test_running => {
                 if self.mode_container.increase {
@@ -156,8 +501,103 @@ impl Main {
 
                 self.connections_container.update(message)
             }
-            PluginChange(_) if !self.test_running => self.plugin_container.update(message),
-            ConstantModeChanged(_) if !self.test_running => {
+            PluginChange(_) if !self.test_running => {
+                let task = self.plugin_container.update(message);
+                self.load_parameters(false);
+
+                task
+            }
+            ParameterChanged(..) if !self.test_running => self.parameters_container.update(message),
+            OpenPluginsFolder | WatchPluginsChanged(_) if !self.test_running => {
+                self.plugin_container.update(message)
+            }
+            RescanPlugins | PluginsChanged(Ok(())) if !self.test_running => {
+                let plugins_path = self.plugins_path.clone();
+                self.load_plugins(plugins_path);
+                Task::none()
+            }
+            // changes made during the test are picked up before the next one
+            PluginsChanged(Ok(())) => {
+                self.rescan_pending = true;
+                Task::none()
+            }
+            PluginsChanged(Err(err)) => {
+                self.plugin_container.watch = false;
+                self.error = Some(err);
+                Task::none()
+            }
+            PluginsFolderOpened(Some(path)) if !self.test_running => {
+                self.load_plugins(path);
+                Task::none()
+            }
+            PrecisionChanged(_) if !self.test_running => self.precision_container.update(message),
+            ProfileChanged(_) | OpenProfile | ProfileOpened(_) if !self.test_running => {
+                self.profile_container.update(message)
+            }
+            RequestTimeoutChanged(_) | ConnectionTimeoutChanged(_) | DrainTimeoutChanged(_)
+                if !self.test_running =>
+            {
+                self.timeouts_container.update(message)
+            }
+            WarmupChanged(_) if !self.test_running => self.warmup_container.update(message),
+            DurationLimitChanged(_) | RequestsLimitChanged(_) if !self.test_running => {
+                self.limits_container.update(message)
+            }
+            ImageWidthChanged(_)
+            | ImageHeightChanged(_)
+            | ImageDpiChanged(_)
+            | LightThemeChanged(_) => self.image_container.update(message),
+            CompareAdd if !self.test_running => {
+                match self.current_variant() {
+                    Ok(variant) => {
+                        self.cant_run = None;
+                        self.compare_container.variants.push(variant);
+                    }
+                    Err(reason) => self.cant_run = Some(reason),
+                }
+
+                Task::none()
+            }
+            CompareRemove(_) if !self.test_running => self.compare_container.update(message),
+            OpenBaseline => {
+                let task = async {
+                    let Some(file) = AsyncFileDialog::new()
+                        .add_filter("results", &["json"])
+                        .pick_file()
+                        .await
+                    else {
+                        return Ok(Vec::new());
+                    };
+                    let path = file.path().to_owned();
+                    let name = file.file_name();
+
+                    let export = tokio::task::spawn_blocking(move || export::Export::read(&path))
+                        .await
+                        .unwrap()?;
+
+                    Ok(containers::Baseline::from_export(&name, export))
+                };
+
+                Task::perform(task, Message::BaselinesLoaded)
+            }
+            BaselinesLoaded(Err(err)) => {
+                self.error = Some(format!("Can't load the baseline: {err}"));
+                Task::none()
+            }
+            StatisticChanged(_)
+            | BandsToggled(_)
+            | P99LineToggled(_)
+            | StackPhasesChanged(_)
+            | BaselinesLoaded(Ok(_))
+            | ClearBaselines
+            | PhaseToggled(..)
+            | SecondarySeriesChanged(_) => {
+                self.chart.update(message);
+                Task::none()
+            }
+            ConstantModeChanged(_) | RateModeChanged(_) | ProfileModeChanged(_)
+                if !self.test_running =>
+            {
                 self.chart.max_connections = None;
                 self.mode_container.update(message)
             }
@@ -166,132 +606,250 @@ impl Main {
                 self.mode_container.update(message)
             }
             RunTest if !self.test_running => {
-                let containers = [
-                    &self.ip_container.0,
-                    &self.port_container.0,
-                    &self.connections_container.text_state,
-                ];
+                if self.rescan_pending {
+                    let plugins_path = self.plugins_path.clone();
+                    self.load_plugins(plugins_path);
+                }
+
+                // the profile replaces the connections number
+                let load_input = if self.mode_container.profile {
+                    &self.profile_container.text_state
+                } else {
+                    &self.connections_container.text_state
+                };
+                let containers = [load_input, &self.precision_container.0];
 
                 let all_correct = containers.iter().map(|c| c.incorrect).all(|k| k == false);
                 let all_not_empty = containers.iter().map(|c| &c.content).all(|s| s.len() > 0);
                 let run_mode_chosen = self.mode_container.chosen();
+                let load_set = if self.mode_container.profile {
+                    self.profile_container.value.is_some()
+                } else {
+                    self.connections_container.value != 0.0
+                };
 
                 if !all_correct
                     || !all_not_empty
+                    || self.timeouts_container.incorrect()
+                    || self.warmup_container.incorrect
+                    || self.limits_container.incorrect()
                     || !run_mode_chosen
-                    || self.connections_container.value == 0.0
+                    || !load_set
                 {
-                    self.cant_run = true;
+                    self.cant_run = Some("Fill all inputs before running!");
                     return Task::none();
                 }
 
-                self.cant_run = false;
+                let variant = match self.current_variant() {
+                    Ok(variant) => variant,
+                    Err(reason) => {
+                        self.cant_run = Some(reason);
+                        return Task::none();
+                    }
+                };
+                let variants: Vec<_> = std::iter::once(variant)
+                    .chain(self.compare_container.variants.iter().cloned())
+                    .collect();
+
+                self.cant_run = None;
                 self.test_running = true;
-                self.chart.clear();
-
-                let plugin_name = self.plugin_container.selected.clone();
-                if self.mode_container.constant {
-                    Task::perform(
-                        ConnectionManager::run_test_constant(
-                            self.connections_container.value as u64,
-                            SocketAddr::from_str(&format!(
-                                "{}:{}",
-                                self.ip_container.content, self.port_container.content
-                            ))
-                            .unwrap(),
-                            self.plugins_path.clone(),
-                            plugin_name,
-                        ),
-                        Message::ConstantTestInitData,
-                    )
-                } else {
-                    Task::perform(
-                        ConnectionManager::run_test_increase(
-                            self.connections_container.value as u64,
-                            SocketAddr::from_str(&format!(
-                                "{}:{}",
-                                self.ip_container.content, self.port_container.content,
-                            ))
-                            .unwrap(),
-                            self.plugins_path.clone(),
-                            plugin_name,
-                        ),
-                        Message::IncreaseTestInitData,
-                    )
+                self.chart.clear(
+                    variants
+                        .iter()
+                        .enumerate()
+                        .map(|(index, variant)| variant.label(index))
+                        .collect(),
+                );
+                self.chart.profile = self
+                    .mode_container
+                    .profile
+                    .then(|| self.profile_container.value.clone())
+                    .flatten();
+                self.runs = variants
+                    .iter()
+                    .enumerate()
+                    .map(|(index, variant)| {
+                        Run::new(
+                            variant.label(index),
+                            variant.plugin_name.clone(),
+                            self.precision_container.value(),
+                        )
+                    })
+                    .collect();
+
+                let tasks: Vec<_> = variants
+                    .into_iter()
+                    .enumerate()
+                    .map(|(target, variant)| {
+                        let config = TestConfig {
+                            target: variant.target,
+                            plugins_path: self.plugins_path.clone(),
+                            plugin_name: variant.plugin_name,
+                            timeouts: self.timeouts_container.value(),
+                            parameters: variant.parameters,
+                            warmup: self.warmup_container.value(),
+                            // used only by the constant and rate modes
+                            limits: self.limits_container.value(),
+                        };
+
+                        self.run_test(target, config)
+                    })
+                    .collect();
+
+                Task::batch(tasks)
+            }
+            ConstantTestInitData(target, Err(err))
+            | IncreaseTestInitData(target, Err(err))
+            | RateTestInitData(target, Err(err))
+            | ProfileTestInitData(target, Err(err)) => {
+                // the other targets aren't tested alone
+                self.send_all(|| benchmark::Message::Stop);
+                for run in &mut self.runs {
+                    run.sender = None;
+                }
+                self.paused = false;
+                self.test_running = false;
+                self.chart.profile = None;
+
+                // the plugin changed since the scan, show why it can't be used now
+                let plugin_name = self
+                    .runs
+                    .get(target)
+                    .map_or_else(String::new, |run| run.plugin_name.clone());
+                let plugins_path = self.plugins_path.clone();
+                self.load_plugins(plugins_path);
+                self.error = Some(format!("Can't run plugin `{plugin_name}`: {}", err.summary));
+
+                Task::none()
+            }
+            ConstantTestInitData(target, Ok(data)) => {
+                let (wrapper, sender) = Arc::into_inner(data).unwrap();
+                if !self.start_run(target, sender) {
+                    return Task::none();
+                }
+
+                Task::run(wrapper, move |value| {
+                    Message::ConstantNewData(target, value)
+                })
+            }
+            IncreaseTestInitData(target, Ok(data)) => {
+                let (wrapper, sender) = Arc::into_inner(data).unwrap();
+                if !self.start_run(target, sender) {
+                    return Task::none();
                 }
+
+                Task::run(wrapper, move |value| {
+                    Message::IncreaseNewData(target, value)
+                })
             }
-            ConstantTestInitData(data) => {
+            RateTestInitData(target, Ok(data)) => {
                 let (wrapper, sender) = Arc::into_inner(data).unwrap();
-                self.sender = Some(sender);
+                if !self.start_run(target, sender) {
+                    return Task::none();
+                }
 
-                Task::run(wrapper, Message::ConstantNewData)
+                Task::run(wrapper, move |value| Message::RateNewData(target, value))
             }
-            IncreaseTestInitData(data) => {
+            ProfileTestInitData(target, Ok(data)) => {
                 let (wrapper, sender) = Arc::into_inner(data).unwrap();
-                self.sender = Some(sender);
+                if !self.start_run(target, sender) {
+                    return Task::none();
+                }
 
-                Task::run(wrapper, Message::IncreaseNewData)
+                Task::run(wrapper, move |value| Message::ProfileNewData(target, value))
             }
-            ConstantNewData(value) => {
-                self.data_points_constant.push(value);
+            ConstantNewData(target, value) | RateNewData(target, (value, _))
+                if value == Outcome::FINISHED =>
+            {
+                // last, partial second
+                self.push_interval(target);
+                self.finish_run(target);
 
                 Task::none()
             }
-            IncreaseNewData(value) => {
-                if self.data_points_increase.first().unwrap_or(&(0, 1u64)).1 != value.1 {
-                    let averaged = {
-                        let n = self.data_points_increase.len() as f64;
-                        let sum = self
-                            .data_points_increase
-                            .iter()
-                            .map(|(x, _)| x * x)
-                            .sum::<u64>() as f64;
-                        (sum / n).sqrt()
-                    };
+            ConstantNewData(target, value) => {
+                self.record(target, value, None);
 
-                    self.data_points_increase.clear();
-                    ConnectionChart::update(
-                        &mut self.chart,
-                        Message::NewChartData(averaged as u64),
-                    );
+                Task::none()
+            }
+            RateNewData(target, (value, in_flight)) => {
+                self.record(target, value, Some(in_flight));
+                if let Some(series) = self.chart.series_mut(target) {
+                    series.in_flight = Some(in_flight);
                 }
 
-                if value.0 == u64::MAX {
-                    self.sender = None;
-                    self.paused = false;
-                    self.test_running = false;
+                Task::none()
+            }
+            ProfileNewData(target, (value, connections)) => {
+                if value == Outcome::FINISHED {
+                    // last, partial second
+                    self.push_interval(target);
+                    self.finish_run(target);
 
                     return Task::none();
                 }
 
-                self.data_points_increase.push(value);
+                self.record(target, value, Some(connections));
+                if let Some(series) = self.chart.series_mut(target) {
+                    series.connections = Some(connections);
+                }
+
+                Task::none()
+            }
+            IncreaseNewData(target, value) => {
+                let Some(run) = self.runs.get_mut(target) else {
+                    return Task::none();
+                };
+
+                if run.increase_step != value.1 {
+                    run.increase_step = value.1;
+                    self.push_interval(target);
+                }
+
+                if value.0 == Outcome::FINISHED {
+                    self.finish_run(target);
+
+                    return Task::none();
+                }
+
+                self.record(target, value.0, Some(value.1));
 
                 Task::none()
             }
             Pause => {
                 self.paused = true;
-                send_msg!(benchmark::Message::Pause);
+                for run in &mut self.runs {
+                    run.interval.pause();
+                }
+                self.send_all(|| benchmark::Message::Pause);
                 Task::none()
             }
             Resume => {
                 self.paused = false;
-                send_msg!(benchmark::Message::Resume);
+                for run in &mut self.runs {
+                    run.interval.resume();
+                }
+                self.send_all(|| benchmark::Message::Resume);
                 Task::none()
             }
             Stop => {
-                send_msg!(benchmark::Message::Stop);
+                self.send_all(|| benchmark::Message::Stop);
 
-                self.sender = None;
+                for run in &mut self.runs {
+                    run.sender = None;
+                }
                 self.paused = false;
                 self.test_running = false;
 
                 Task::none()
             }
             Save => {
-                let chart = self.chart.clone();
+                let chart = Box::new(self.chart.clone());
                 let task = async {
                     let file = AsyncFileDialog::new()
                         .add_filter("image", &["png", "jpg"])
+                        .add_filter("vector image", &["svg"])
+                        .add_filter("results", &["csv", "json"])
                         .save_file()
                         .await;
 
@@ -300,61 +858,149 @@ impl Main {
 
                 Task::perform(task, |result| Message::FileDialog(result.0, result.1))
             }
-            FileDialog(Some(file_handle), chart) => {
-                std::thread::spawn(move || {
-                    let path = file_handle.path();
-                    let root = BitMapBackend::new(path, (1920, 1080)).into_drawing_area();
-                    root.fill(&plotters::style::RGBColor(32, 34, 37))
-                        .expect("Unable to create chart");
-
-                    let chart_builder = ChartBuilder::on(&root);
-                    chart.build_chart_png(chart_builder);
-
-                    root.present().expect("Unable to create chart");
-                });
+            FileDialog(Some(file_handle), _)
+                if file_handle
+                    .path()
+                    .extension()
+                    .is_some_and(|extension| extension == "json") =>
+            {
+                self.export_results(file_handle.path().to_owned(), None)
+            }
+            FileDialog(Some(file_handle), _)
+                if file_handle
+                    .path()
+                    .extension()
+                    .is_some_and(|extension| extension == "csv") =>
+            {
+                let path = file_handle.path().to_owned();
+                let mut dialog = AsyncFileDialog::new()
+                    .set_title("Save chart points")
+                    .set_file_name(export::Export::intervals_file_name(&path))
+                    .add_filter("results", &["csv"]);
+                if let Some(directory) = path.parent() {
+                    dialog = dialog.set_directory(directory);
+                }
 
+                Task::perform(dialog.save_file(), move |file| {
+                    Message::IntervalsFileDialog(path.clone(), file)
+                })
+            }
+            IntervalsFileDialog(path, Some(file_handle)) => {
+                self.export_results(path, Some(file_handle.path().to_owned()))
+            }
+            Exported(Err(err)) => {
+                self.error = Some(format!("Can't export the results: {err}"));
                 Task::none()
             }
-            Tick if self.test_running && !self.paused && self.mode_container.constant => {
-                let averaged = {
-                    let n = self.data_points_constant.len() as f64;
-                    let sum = self.data_points_constant.iter().map(|x| x * x).sum::<u64>() as f64;
-                    (sum / n).sqrt()
+            FileDialog(Some(file_handle), chart) => {
+                let Some(options) = self.image_container.value() else {
+                    self.error = Some("Can't save the chart: bad image size".to_owned());
+                    return Task::none();
                 };
 
-                self.data_points_constant.clear();
+                let save = move || {
+                    let path = file_handle.path();
+                    let size = (options.width, options.height);
+
+                    if path.extension().is_some_and(|extension| extension == "svg") {
+                        let root = SVGBackend::new(path, size).into_drawing_area();
+                        draw_image(root, &chart, &options)
+                    } else {
+                        let root = BitMapBackend::new(path, size).into_drawing_area();
+                        draw_image(root, &chart, &options)
+                    }
+                };
 
-                ConnectionChart::update(&mut self.chart, Message::NewChartData(averaged as u64));
+                // big images take a while to encode
+                Task::perform(
+                    async move {
+                        tokio::task::spawn_blocking(save)
+                            .await
+                            .unwrap_or_else(|err| Err(err.to_string()))
+                    },
+                    Message::ImageSaved,
+                )
+            }
+            ImageSaved(Err(err)) => {
+                self.error = Some(format!("Can't save the chart: {err}"));
+                Task::none()
+            }
+            Tick if self.test_running
+                && !self.paused
+                && (self.mode_container.constant
+                    || self.mode_container.rate
+                    || self.mode_container.profile) =>
+            {
+                for target in 0..self.runs.len() {
+                    if !self.runs[target].finished {
+                        self.push_interval(target);
+                    }
+                }
 
                 Task::none()
             }
             _ => Task::none(),
         }
     }
-    fn view(&self) -> Element<Message> {
-        return container(column![
-            row![
-                self.ip_container.view(),
-                self.port_container.view(),
-                self.plugin_container.view(),
-                self.mode_container.view(),
-            ]
-            .push_maybe(
-                self.mode_container.chosen().then_some(
-                    self.connections_container
-                        .view(self.mode_container.constant)
-                )
-            )
-            .push(
-                self.buttons_container
-                    .view(self.cant_run, self.test_running, self.paused)
-            )
-            .spacing(10),
-            container(self.chart.view())
+    fn view(&self) -> Element<'_, Message> {
+        // run is disabled until there is something to run
+        let run_blocked = self
+            .plugin_container
+            .selected()
+            .is_none()
+            .then_some("Select a plugin that can be loaded!");
+
+        let banner = self.error.as_ref().map(|error| {
+            container(text(error).color(Color::from_rgb(1.0, 0.3, 0.3)))
                 .width(Length::Fill)
-                .height(Length::Fill)
-        ])
+                .padding(5)
+                .style(container::rounded_box)
+        });
+
+        container(
+            column![].push_maybe(banner).extend([
+                row![
+                    self.ip_container.view(),
+                    self.port_container.view(),
+                    self.plugin_container.view(),
+                    self.parameters_container.view(),
+                    self.mode_container.view(),
+                ]
+                .push_maybe(if self.mode_container.profile {
+                    Some(self.profile_container.view())
+                } else {
+                    self.mode_container
+                        .chosen()
+                        .then_some(self.connections_container.view(&self.mode_container))
+                })
+                .push(self.buttons_container.view(
+                    run_blocked.or(self.cant_run),
+                    run_blocked.is_some(),
+                    self.test_running,
+                    self.paused,
+                ))
+                .spacing(10)
+                .into(),
+                row![
+                    self.precision_container.view(),
+                    self.timeouts_container.view(),
+                    self.warmup_container.view(),
+                ]
+                .push_maybe(
+                    (self.mode_container.constant || self.mode_container.rate)
+                        .then(|| self.limits_container.view()),
+                )
+                .push(self.compare_container.view(self.test_running))
+                .push(self.image_container.view())
+                .spacing(10)
+                .into(),
+                container(self.chart.view())
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .into(),
+            ]),
+        )
         .padding(5)
-        .into();
+        .into()
     }
 }
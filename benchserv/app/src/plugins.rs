@@ -0,0 +1,310 @@
+use abi_stable::library::LibraryError;
+use futures::Stream;
+use interface::{ConnectionPlugin_Ref, Metadata, Parameter, load_root_module_from_file};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+use tempfile::TempDir;
+
+const EXTENSIONS: [&str; 3] = ["dll", "so", "dylib"];
+
+/// quiet time after the last change before the directory is rescanned
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// plugin found in the plugins directory
+#[derive(Clone, Debug)]
+pub struct PluginInfo {
+    /// file name without the extension, used to load the plugin
+    pub name: String,
+    pub metadata: Metadata,
+    pub parameters: Vec<Parameter>,
+    /// reason the plugin can't be used
+    pub error: Option<LoadError>,
+}
+
+impl PluginInfo {
+    /// loads the plugin to read its metadata, errors are kept in the info
+    pub fn load(plugins_path: &str, name: &str) -> Self {
+        match load(plugins_path, name) {
+            Ok(plugin) => PluginInfo {
+                name: name.to_owned(),
+                metadata: plugin.metadata(),
+                parameters: plugin.parameters().to_vec(),
+                error: None,
+            },
+            Err(err) => PluginInfo {
+                name: name.to_owned(),
+                metadata: Metadata::default(),
+                parameters: Vec::new(),
+                error: Some(LoadError::from(&err)),
+            },
+        }
+    }
+    pub fn compatible(&self) -> bool {
+        self.error.is_none()
+    }
+    pub fn supports(&self, ip: &SocketAddr) -> bool {
+        match ip {
+            SocketAddr::V4(_) => self.metadata.families.ipv4,
+            SocketAddr::V6(_) => self.metadata.families.ipv6,
+        }
+    }
+    /// display name declared by the plugin or the file name
+    pub fn title(&self) -> &str {
+        if self.metadata.name.is_empty() {
+            &self.name
+        } else {
+            self.metadata.name.as_str()
+        }
+    }
+}
+
+/// why the plugin couldn't be loaded
+#[derive(Clone, Debug)]
+pub struct LoadError {
+    /// short explanation shown next to the plugin
+    pub summary: String,
+    /// full error returned by the loader
+    pub details: String,
+}
+
+impl From<&LibraryError> for LoadError {
+    fn from(err: &LibraryError) -> Self {
+        let summary = match err {
+            LibraryError::OpenError { .. } => "can't open the library",
+            LibraryError::GetSymbolError { .. } => "not a benchserv plugin",
+            LibraryError::IncompatibleVersionNumber { .. } | LibraryError::ParseVersionError(_) => {
+                "built for a different interface version"
+            }
+            LibraryError::AbiInstability(_)
+            | LibraryError::InvalidAbiHeader(_)
+            | LibraryError::InvalidCAbi { .. } => "built against a different interface, rebuild it",
+            LibraryError::RootModule { .. } => "plugin failed to initialize",
+            _ => "can't load the plugin",
+        };
+
+        LoadError {
+            summary: summary.to_owned(),
+            details: err.to_string(),
+        }
+    }
+}
+
+/// loads every plugin in the directory, sorted by name
+pub fn scan(plugins_path: &str) -> io::Result<Vec<PluginInfo>> {
+    let mut names = vec![];
+    for entry in std::fs::read_dir(plugins_path)? {
+        let Ok(entry) = entry else { continue };
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if !file_type.is_file() {
+            continue;
+        }
+
+        let path = entry.path();
+        let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        if is_library(&path) {
+            names.push(name.to_owned());
+        }
+    }
+
+    names.sort();
+
+    Ok(names
+        .iter()
+        .map(|name| PluginInfo::load(plugins_path, name))
+        .collect())
+}
+
+/// loads the plugin from a copy made for its current build, libraries stay
+/// loaded until the app exits so loading a rebuilt plugin from the same path
+/// would return the old one, every rebuild loaded leaks the memory of the
+/// previous library
+pub fn load(plugins_path: &str, name: &str) -> Result<ConnectionPlugin_Ref, LibraryError> {
+    let path = plugin_path(plugins_path, name);
+    // without the copy rebuilt plugins aren't picked up, the rest still works
+    let path = versioned_copy(&path).unwrap_or(path);
+
+    load_root_module_from_file(&path)
+}
+
+/// copies of the loaded plugins in a directory only this process can use,
+/// other users could put their own libraries in a shared one
+#[derive(Debug)]
+struct PluginCopies {
+    dir: TempDir,
+    /// plugin path to the copy of its last loaded build
+    copies: BTreeMap<PathBuf, PluginCopy>,
+    last_copy: u64,
+}
+
+#[derive(Debug)]
+struct PluginCopy {
+    path: PathBuf,
+    modified: SystemTime,
+    len: u64,
+}
+
+impl PluginCopies {
+    fn new() -> io::Result<Self> {
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("benchserv-plugins-");
+        #[cfg(unix)]
+        builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o700));
+
+        Ok(PluginCopies {
+            dir: builder.tempdir()?,
+            copies: BTreeMap::new(),
+            last_copy: 0,
+        })
+    }
+}
+
+static COPIES: Mutex<Option<PluginCopies>> = Mutex::new(None);
+
+fn versioned_copy(path: &Path) -> io::Result<PathBuf> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified()?;
+
+    let mut copies = COPIES.lock().unwrap();
+    let copies = match &mut *copies {
+        Some(copies) => copies,
+        None => copies.insert(PluginCopies::new()?),
+    };
+
+    if let Some(copy) = copies.copies.get(path)
+        && copy.modified == modified
+        && copy.len == metadata.len()
+    {
+        return Ok(copy.path.clone());
+    }
+
+    // the extension stays at the end, windows needs it to load the library
+    copies.last_copy += 1;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let copy_path = copies
+        .dir
+        .path()
+        .join(format!("{}-{file_name}", copies.last_copy));
+
+    let mut copy = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&copy_path)?;
+    if let Err(err) = io::copy(&mut File::open(path)?, &mut copy) {
+        let _ = fs::remove_file(&copy_path);
+        return Err(err);
+    }
+
+    let copy = PluginCopy {
+        path: copy_path.clone(),
+        modified,
+        len: metadata.len(),
+    };
+    // only the copy of the current build is kept, it fails on windows while
+    // a running test still uses the library
+    if let Some(older) = copies.copies.insert(path.to_owned(), copy) {
+        let _ = fs::remove_file(older.path);
+    }
+
+    Ok(copy_path)
+}
+
+/// removes the copies once the app is done with the plugins
+pub fn remove_copies() {
+    COPIES.lock().unwrap().take();
+}
+
+/// yields once libraries in the directory stop changing, the error is sent
+/// when the directory can't be watched
+pub fn watch(plugins_path: String) -> impl Stream<Item = Result<(), String>> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else { return };
+
+        // loading the plugins reads them too
+        let is_change = !matches!(event.kind, EventKind::Access(_));
+        if is_change && event.paths.iter().any(|path| is_library(path)) {
+            let _ = tx.send(());
+        }
+    })
+    .and_then(|mut watcher| {
+        watcher.watch(Path::new(&plugins_path), RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    })
+    .map_err(|err| format!("Can't watch the plugins directory `{plugins_path}`: {err}"));
+
+    futures::stream::unfold(Some((watcher, rx)), |state| async move {
+        let (watcher, mut rx) = state?;
+        let watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(err) => return Some((Err(err), None)),
+        };
+
+        rx.recv().await?;
+        // building writes the library many times, wait until it's done
+        while let Ok(event) = tokio::time::timeout(WATCH_DEBOUNCE, rx.recv()).await {
+            event?;
+        }
+
+        // the watcher stops when dropped, it's kept as long as the stream
+        Some((Ok(()), Some((Ok(watcher), rx))))
+    })
+}
+
+fn is_library(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension))
+}
+
+/// plugin can be given with or without the library extension
+fn plugin_path(plugins_path: &str, plugin_name: &str) -> PathBuf {
+    let path = Path::new(plugins_path).join(plugin_name);
+    if path.is_file() {
+        return path;
+    }
+
+    EXTENSIONS
+        .iter()
+        .map(|extension| Path::new(plugins_path).join(format!("{plugin_name}.{extension}")))
+        .find(|path| path.is_file())
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuilt_plugin_gets_new_copy() {
+        let plugins = tempfile::tempdir().unwrap();
+        let path = plugins.path().join("plugin.so");
+        fs::write(&path, "first build").unwrap();
+
+        let first = versioned_copy(&path).unwrap();
+        assert_eq!(versioned_copy(&path).unwrap(), first);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "first build");
+
+        fs::write(&path, "second build").unwrap();
+        let second = versioned_copy(&path).unwrap();
+        assert_ne!(second, first);
+        // the extension stays at the end
+        assert!(second.to_string_lossy().ends_with("-plugin.so"));
+        assert_eq!(fs::read_to_string(&second).unwrap(), "second build");
+        assert!(!first.exists());
+    }
+}
@@ -0,0 +1,332 @@
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+/// single step of the load profile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// linearly change the number of connections over the duration
+    Ramp {
+        connections: u64,
+        duration: Duration,
+    },
+    /// keep the current number of connections
+    Hold { duration: Duration },
+    /// jump to the number of connections and go back after the duration
+    Spike {
+        connections: u64,
+        duration: Duration,
+    },
+}
+
+impl Stage {
+    pub fn duration(&self) -> Duration {
+        match *self {
+            Stage::Ramp { duration, .. }
+            | Stage::Hold { duration }
+            | Stage::Spike { duration, .. } => duration,
+        }
+    }
+    /// short description shown next to the stage boundary on the chart
+    pub fn label(&self) -> String {
+        match self {
+            Stage::Ramp { connections, .. } => format!("ramp {connections}"),
+            Stage::Hold { .. } => "hold".to_owned(),
+            Stage::Spike { connections, .. } => format!("spike {connections}"),
+        }
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let duration = format_duration(self.duration());
+
+        match self {
+            Stage::Hold { .. } => write!(f, "hold {duration}"),
+            _ => write!(f, "{} {duration}", self.label()),
+        }
+    }
+}
+
+impl FromStr for Stage {
+    type Err = String;
+
+    /// `ramp 500 60s`, `hold 2m`, `spike 2000 10s`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<_> = s.split_whitespace().collect();
+
+        let connections = |word: &str| {
+            word.parse::<u64>()
+                .map_err(|_| format!("bad connections number `{word}`"))
+        };
+
+        match words[..] {
+            ["ramp", to, duration] => Ok(Stage::Ramp {
+                connections: connections(to)?,
+                duration: parse_duration(duration)?,
+            }),
+            ["hold", duration] => Ok(Stage::Hold {
+                duration: parse_duration(duration)?,
+            }),
+            ["spike", to, duration] => Ok(Stage::Spike {
+                connections: connections(to)?,
+                duration: parse_duration(duration)?,
+            }),
+            _ => Err(format!("unknown stage `{s}`")),
+        }
+    }
+}
+
+/// list of stages executed one after another, starting from zero connections
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub stages: Vec<Stage>,
+}
+
+impl Profile {
+    pub fn duration(&self) -> Duration {
+        self.stages.iter().fold(Duration::ZERO, |total, stage| {
+            total.saturating_add(stage.duration())
+        })
+    }
+    /// number of connections that should be open after `elapsed` time,
+    /// none once the profile is over
+    pub fn connections_at(&self, elapsed: Duration) -> Option<u64> {
+        let mut level = 0;
+        let mut stage_start = Duration::ZERO;
+
+        for stage in &self.stages {
+            let stage_end = stage_start + stage.duration();
+
+            if elapsed < stage_end {
+                let progress =
+                    (elapsed - stage_start).as_secs_f64() / stage.duration().as_secs_f64();
+
+                return Some(match *stage {
+                    Stage::Ramp { connections, .. } => {
+                        let change = (connections as f64 - level as f64) * progress;
+                        (level as f64 + change).round() as u64
+                    }
+                    Stage::Hold { .. } => level,
+                    Stage::Spike { connections, .. } => connections,
+                });
+            }
+
+            if let Stage::Ramp { connections, .. } = *stage {
+                level = connections;
+            }
+            stage_start = stage_end;
+        }
+
+        None
+    }
+    /// start of every stage in seconds since the start of the test
+    pub fn boundaries(&self) -> Vec<(u64, String)> {
+        let mut stage_start = Duration::ZERO;
+
+        self.stages
+            .iter()
+            .map(|stage| {
+                let boundary = (stage_start.as_secs(), stage.label());
+                stage_start += stage.duration();
+                boundary
+            })
+            .collect()
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stages: Vec<_> = self.stages.iter().map(Stage::to_string).collect();
+
+        write!(f, "{}", stages.join("; "))
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    /// stages separated by semicolons or new lines, `#` starts a comment
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stages = s
+            .lines()
+            .map(|line| line.split('#').next().unwrap())
+            .flat_map(|line| line.split(';'))
+            .map(str::trim)
+            .filter(|stage| !stage.is_empty())
+            .map(Stage::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if stages.is_empty() {
+            return Err("profile has no stages".to_owned());
+        }
+        // `connections_at` and the chart add up the stages unchecked
+        stages
+            .iter()
+            .try_fold(Duration::ZERO, |total, stage| {
+                total.checked_add(stage.duration())
+            })
+            .ok_or("profile is too long")?;
+
+        Ok(Profile { stages })
+    }
+}
+
+/// `500ms`, `60s`, `2m`, `1h`
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let err = || format!("bad duration `{s}`");
+
+    let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(err)?;
+    let (value, unit) = s.split_at(split);
+    let value: u64 = value.parse().map_err(|_| err())?;
+
+    let seconds = match unit {
+        "ms" => return Ok(Duration::from_millis(value)),
+        "s" => Some(value),
+        "m" => value.checked_mul(60),
+        "h" => value.checked_mul(3600),
+        _ => return Err(err()),
+    };
+
+    seconds
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration `{s}` is too long"))
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+
+    if millis.is_multiple_of(3_600_000) && millis > 0 {
+        format!("{}h", millis / 3_600_000)
+    } else if millis.is_multiple_of(60_000) && millis > 0 {
+        format!("{}m", millis / 60_000)
+    } else if millis.is_multiple_of(1000) {
+        format!("{}s", millis / 1000)
+    } else {
+        format!("{millis}ms")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("60s"), Ok(secs(60)));
+        assert_eq!(parse_duration("2m"), Ok(secs(120)));
+        assert_eq!(parse_duration("1h"), Ok(secs(3600)));
+        assert_eq!(parse_duration("0s"), Ok(Duration::ZERO));
+
+        for bad in ["", "10", "s", "1.5s", "-1s", "10 s", "10d"] {
+            assert!(parse_duration(bad).is_err(), "`{bad}` was accepted");
+        }
+        assert_eq!(
+            parse_duration("18446744073709551615h"),
+            Err("duration `18446744073709551615h` is too long".to_owned())
+        );
+        assert_eq!(
+            parse_duration("18446744073709551616s"),
+            Err("bad duration `18446744073709551616s`".to_owned())
+        );
+    }
+
+    #[test]
+    fn durations_are_formatted_in_largest_unit() {
+        for duration in ["500ms", "1500ms", "0s", "90s", "2m", "1h", "90m"] {
+            assert_eq!(format_duration(parse_duration(duration).unwrap()), duration);
+        }
+    }
+
+    #[test]
+    fn profile() {
+        let profile: Profile = "ramp 500 60s; hold 2m # comment\n\n spike 2000 10s;"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            profile.stages,
+            [
+                Stage::Ramp {
+                    connections: 500,
+                    duration: secs(60),
+                },
+                Stage::Hold {
+                    duration: secs(120)
+                },
+                Stage::Spike {
+                    connections: 2000,
+                    duration: secs(10),
+                },
+            ]
+        );
+        assert_eq!(profile.duration(), secs(190));
+        assert_eq!(profile.to_string(), "ramp 500 1m; hold 2m; spike 2000 10s");
+        assert_eq!(profile.to_string().parse(), Ok(profile.clone()));
+        assert_eq!(
+            profile.boundaries(),
+            [
+                (0, "ramp 500".to_owned()),
+                (60, "hold".to_owned()),
+                (180, "spike 2000".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_profiles() {
+        assert_eq!(
+            "# nothing".parse::<Profile>(),
+            Err("profile has no stages".to_owned())
+        );
+        assert_eq!(
+            "ramp 10 1s; jump 5 1s".parse::<Profile>(),
+            Err("unknown stage `jump 5 1s`".to_owned())
+        );
+        assert_eq!(
+            "ramp ten 1s".parse::<Profile>(),
+            Err("bad connections number `ten`".to_owned())
+        );
+        assert_eq!(
+            "hold 10 1s".parse::<Profile>(),
+            Err("unknown stage `hold 10 1s`".to_owned())
+        );
+        assert_eq!(
+            "hold 18446744073709551615s; hold 1s".parse::<Profile>(),
+            Err("profile is too long".to_owned())
+        );
+    }
+
+    #[test]
+    fn connections_at() {
+        let profile: Profile = "ramp 100 10s; hold 10s; spike 500 5s; ramp 0 10s"
+            .parse()
+            .unwrap();
+
+        assert_eq!(profile.connections_at(Duration::ZERO), Some(0));
+        assert_eq!(profile.connections_at(secs(5)), Some(50));
+        assert_eq!(
+            profile.connections_at(Duration::from_millis(9999)),
+            Some(100)
+        );
+        assert_eq!(profile.connections_at(secs(10)), Some(100));
+        assert_eq!(profile.connections_at(secs(19)), Some(100));
+        assert_eq!(profile.connections_at(secs(20)), Some(500));
+        assert_eq!(profile.connections_at(secs(24)), Some(500));
+        // the spike goes back to the level before it
+        assert_eq!(profile.connections_at(secs(25)), Some(100));
+        assert_eq!(profile.connections_at(secs(30)), Some(50));
+        assert_eq!(profile.connections_at(secs(35)), None);
+    }
+
+    #[test]
+    fn empty_stages_are_skipped() {
+        let profile: Profile = "ramp 100 0s; hold 10s".parse().unwrap();
+
+        assert_eq!(profile.connections_at(Duration::ZERO), Some(100));
+        assert_eq!(profile.connections_at(secs(10)), None);
+    }
+}
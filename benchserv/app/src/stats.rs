@@ -0,0 +1,688 @@
+use crate::benchmark::{Metric, Outcome};
+use hdrhistogram::Histogram;
+use interface::ErrorKind;
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+/// significant figures kept by the histograms unless user chooses otherwise
+pub const DEFAULT_PRECISION: u8 = 3;
+
+/// highest precision supported by the histograms
+pub const MAX_PRECISION: u8 = 5;
+
+/// latencies in nanoseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Percentiles {
+    /// lower ends of the bands around the median
+    pub p10: u64,
+    pub p25: u64,
+    pub p50: u64,
+    pub p75: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub max: u64,
+}
+
+/// statistic of the interval shown on the chart
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Statistic {
+    #[default]
+    P50,
+    P90,
+    P99,
+    P999,
+    Max,
+}
+
+impl Statistic {
+    pub const ALL: [Statistic; 5] = [
+        Statistic::P50,
+        Statistic::P90,
+        Statistic::P99,
+        Statistic::P999,
+        Statistic::Max,
+    ];
+
+    pub fn of(&self, percentiles: &Percentiles) -> u64 {
+        match self {
+            Statistic::P50 => percentiles.p50,
+            Statistic::P90 => percentiles.p90,
+            Statistic::P99 => percentiles.p99,
+            Statistic::P999 => percentiles.p999,
+            Statistic::Max => percentiles.max,
+        }
+    }
+}
+
+impl Display for Statistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Statistic::P50 => "p50",
+            Statistic::P90 => "p90",
+            Statistic::P99 => "p99",
+            Statistic::P999 => "p99.9",
+            Statistic::Max => "max",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// latencies recorded during one interval of the test
+#[derive(Clone, Debug)]
+pub struct LatencyHistogram(Histogram<u64>);
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram::new(DEFAULT_PRECISION)
+    }
+}
+
+impl LatencyHistogram {
+    /// `precision` is the number of significant figures kept for every value
+    pub fn new(precision: u8) -> Self {
+        let precision = precision.clamp(1, MAX_PRECISION);
+
+        LatencyHistogram(Histogram::new(precision).expect("precision is always in range"))
+    }
+    pub fn record(&mut self, latency: Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+
+        // recording resizes the histogram, saturating one would clamp the value instead
+        if self.0.record(nanos).is_err() {
+            self.0.saturating_record(nanos);
+        }
+    }
+    pub fn len(&self) -> u64 {
+        self.0.len()
+    }
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+    pub fn percentiles(&self) -> Percentiles {
+        if self.0.is_empty() {
+            return Percentiles::default();
+        }
+
+        Percentiles {
+            p10: self.0.value_at_quantile(0.1),
+            p25: self.0.value_at_quantile(0.25),
+            p50: self.0.value_at_quantile(0.5),
+            p75: self.0.value_at_quantile(0.75),
+            p90: self.0.value_at_quantile(0.9),
+            p99: self.0.value_at_quantile(0.99),
+            p999: self.0.value_at_quantile(0.999),
+            max: self.0.max(),
+        }
+    }
+}
+
+/// failed requests grouped by their category
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ErrorCounts(BTreeMap<ErrorKind, u64>);
+
+impl ErrorCounts {
+    pub fn record(&mut self, kind: ErrorKind) {
+        *self.0.entry(kind).or_default() += 1;
+    }
+    pub fn total(&self) -> u64 {
+        self.0.values().sum()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (ErrorKind, u64)> + '_ {
+        self.0.iter().map(|(kind, count)| (*kind, *count))
+    }
+}
+
+impl Display for ErrorCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts = self
+            .iter()
+            .map(|(kind, count)| format!("{kind}: {count}"))
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", counts.join(", "))
+    }
+}
+
+/// summary of one chart point
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Interval {
+    pub percentiles: Percentiles,
+    /// successful and failed requests
+    pub requests: u64,
+    pub errors: ErrorCounts,
+    /// time covered by the interval, without pauses
+    pub duration: Duration,
+    /// latencies of the request phases marked by the plugin
+    pub phases: Vec<(String, Percentiles)>,
+    /// counters summed and gauges averaged over the interval
+    pub metrics: Vec<(String, Metric)>,
+    /// has requests finished during the warm-up, such intervals are charted
+    /// but left out of the totals
+    pub warmup: bool,
+}
+
+impl Interval {
+    /// percentage of failed requests
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+
+        self.errors.total() as f64 / self.requests as f64 * 100.0
+    }
+    pub fn phase(&self, name: &str) -> Option<&Percentiles> {
+        self.phases
+            .iter()
+            .find(|(phase, _)| phase == name)
+            .map(|(_, percentiles)| percentiles)
+    }
+    /// counter per second or the average of the gauge
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        let (_, metric) = self.metrics.iter().find(|(metric, _)| metric == name)?;
+
+        Some(match *metric {
+            Metric::Counter(count) => Interval::per_second(count, self.duration),
+            Metric::Gauge(value) => value,
+        })
+    }
+    /// successful and failed requests per second
+    pub fn throughput(&self) -> f64 {
+        Interval::per_second(self.requests, self.duration)
+    }
+    /// failed requests per second
+    pub fn failed_throughput(&self) -> f64 {
+        Interval::per_second(self.errors.total(), self.duration)
+    }
+    fn per_second(count: u64, duration: Duration) -> f64 {
+        if duration.is_zero() {
+            return 0.0;
+        }
+
+        count as f64 / duration.as_secs_f64()
+    }
+    /// adds counters reported for a request to the totals, gauges are skipped
+    fn add_counters(&mut self, metrics: &[(String, Metric)]) {
+        for (name, metric) in metrics {
+            let Metric::Counter(count) = metric else {
+                continue;
+            };
+
+            match self.metrics.iter_mut().find(|(total, _)| total == name) {
+                Some((_, Metric::Counter(total))) => *total += count,
+                Some(_) => {}
+                None => self.metrics.push((name.clone(), *metric)),
+            }
+        }
+    }
+}
+
+/// reports of a metric collected during the interval
+#[derive(Clone, Copy, Debug)]
+enum MetricTotal {
+    Counter(u64),
+    Gauge { sum: f64, reports: u64 },
+}
+
+/// collects requests of the current interval
+#[derive(Clone, Debug)]
+pub struct IntervalStats {
+    histogram: LatencyHistogram,
+    errors: ErrorCounts,
+    /// kept in the order the phases were first seen
+    phases: Vec<(String, LatencyHistogram)>,
+    /// kept between intervals so counters without reports show zero
+    metrics: Vec<(String, MetricTotal)>,
+    warmup: bool,
+    /// counts and latencies of the whole test without the warm-up
+    run: Interval,
+    run_histogram: LatencyHistogram,
+    /// first request after the warm-up
+    run_started: Option<Instant>,
+    precision: u8,
+    started: Instant,
+    /// time spent paused since the interval started
+    paused: Duration,
+    paused_at: Option<Instant>,
+}
+
+impl Default for IntervalStats {
+    fn default() -> Self {
+        IntervalStats::new(DEFAULT_PRECISION)
+    }
+}
+
+impl IntervalStats {
+    pub fn new(precision: u8) -> Self {
+        IntervalStats {
+            histogram: LatencyHistogram::new(precision),
+            errors: ErrorCounts::default(),
+            phases: Vec::new(),
+            metrics: Vec::new(),
+            warmup: false,
+            run: Interval::default(),
+            run_histogram: LatencyHistogram::new(precision),
+            run_started: None,
+            precision,
+            started: Instant::now(),
+            paused: Duration::ZERO,
+            paused_at: None,
+        }
+    }
+    /// stops counting the interval duration until `resume`
+    pub fn pause(&mut self) {
+        self.pause_at(Instant::now());
+    }
+    fn pause_at(&mut self, now: Instant) {
+        self.paused_at.get_or_insert(now);
+    }
+    pub fn resume(&mut self) {
+        self.resume_at(Instant::now());
+    }
+    fn resume_at(&mut self, now: Instant) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused += now.saturating_duration_since(paused_at);
+        }
+    }
+    pub fn record(&mut self, outcome: &Outcome) {
+        self.warmup |= outcome.warmup();
+        if !outcome.warmup() {
+            self.record_run(outcome);
+        }
+
+        match outcome {
+            Outcome::Success(sample) => {
+                self.histogram.record(sample.latency);
+
+                for (name, duration) in &sample.phases {
+                    self.phase_histogram(name).record(*duration);
+                }
+                self.record_metrics(&sample.metrics);
+            }
+            Outcome::Failure(failure) => {
+                self.errors.record(failure.kind);
+                self.record_metrics(&failure.metrics);
+            }
+        }
+    }
+    /// counted per request, the interval in which the warm-up ends is shared
+    fn record_run(&mut self, outcome: &Outcome) {
+        self.run_started.get_or_insert_with(Instant::now);
+        self.run.requests += 1;
+
+        let metrics = match outcome {
+            Outcome::Success(sample) => {
+                self.run_histogram.record(sample.latency);
+                &sample.metrics
+            }
+            Outcome::Failure(failure) => {
+                self.run.errors.record(failure.kind);
+                &failure.metrics
+            }
+        };
+        self.run.add_counters(metrics);
+    }
+    fn record_metrics(&mut self, metrics: &[(String, Metric)]) {
+        for (name, metric) in metrics {
+            let total = self.metrics.iter_mut().find(|(total, _)| total == name);
+
+            match (total, *metric) {
+                (Some((_, MetricTotal::Counter(total))), Metric::Counter(count)) => *total += count,
+                (Some((_, MetricTotal::Gauge { sum, reports })), Metric::Gauge(value)) => {
+                    *sum += value;
+                    *reports += 1;
+                }
+                // the kind of the metric is set by its first report
+                (Some(_), _) => {}
+                (None, Metric::Counter(count)) => {
+                    self.metrics
+                        .push((name.clone(), MetricTotal::Counter(count)));
+                }
+                (None, Metric::Gauge(value)) => {
+                    let total = MetricTotal::Gauge {
+                        sum: value,
+                        reports: 1,
+                    };
+                    self.metrics.push((name.clone(), total));
+                }
+            }
+        }
+    }
+    fn phase_histogram(&mut self, name: &str) -> &mut LatencyHistogram {
+        let index = match self.phases.iter().position(|(phase, _)| phase == name) {
+            Some(index) => index,
+            None => {
+                let histogram = LatencyHistogram::new(self.precision);
+                self.phases.push((name.to_owned(), histogram));
+                self.phases.len() - 1
+            }
+        };
+
+        &mut self.phases[index].1
+    }
+    /// summarises the interval and starts a new one
+    pub fn finish(&mut self) -> Interval {
+        self.finish_at(Instant::now())
+    }
+    fn finish_at(&mut self, now: Instant) -> Interval {
+        let end = self.paused_at.unwrap_or(now);
+
+        let interval = Interval {
+            percentiles: self.histogram.percentiles(),
+            requests: self.histogram.len() + self.errors.total(),
+            errors: std::mem::take(&mut self.errors),
+            duration: end
+                .saturating_duration_since(self.started)
+                .saturating_sub(self.paused),
+            phases: self
+                .phases
+                .iter()
+                .filter(|(_, histogram)| histogram.len() > 0)
+                .map(|(name, histogram)| (name.clone(), histogram.percentiles()))
+                .collect(),
+            metrics: self
+                .metrics
+                .iter()
+                .filter_map(|(name, total)| match *total {
+                    MetricTotal::Counter(count) => Some((name.clone(), Metric::Counter(count))),
+                    MetricTotal::Gauge { reports: 0, .. } => None,
+                    MetricTotal::Gauge { sum, reports } => {
+                        Some((name.clone(), Metric::Gauge(sum / reports as f64)))
+                    }
+                })
+                .collect(),
+            warmup: std::mem::take(&mut self.warmup),
+        };
+
+        // the run starts with its first request, not with the interval
+        if let Some(run_started) = self.run_started {
+            let from = std::cmp::max(run_started, self.started);
+            self.run.duration += end
+                .saturating_duration_since(from)
+                .saturating_sub(self.paused);
+        }
+
+        self.histogram.reset();
+        for (_, histogram) in &mut self.phases {
+            histogram.reset();
+        }
+        for (_, total) in &mut self.metrics {
+            *total = match total {
+                MetricTotal::Counter(_) => MetricTotal::Counter(0),
+                MetricTotal::Gauge { .. } => MetricTotal::Gauge {
+                    sum: 0.0,
+                    reports: 0,
+                },
+            };
+        }
+        self.started = now;
+        self.paused = Duration::ZERO;
+        if self.paused_at.is_some() {
+            self.paused_at = Some(now);
+        }
+
+        interval
+    }
+    /// totals and percentiles of the test without the warm-up, the duration
+    /// is updated by `finish`
+    pub fn summary(&self) -> Interval {
+        Interval {
+            percentiles: self.run_histogram.percentiles(),
+            ..self.run.clone()
+        }
+    }
+}
+
+/// unit used to display latencies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeUnit {
+    Nanos,
+    Micros,
+    Millis,
+    Seconds,
+}
+
+impl TimeUnit {
+    /// the largest unit in which `nanos` is at least one
+    pub fn fitting(nanos: u64) -> Self {
+        match nanos {
+            0..1_000 => TimeUnit::Nanos,
+            1_000..1_000_000 => TimeUnit::Micros,
+            1_000_000..1_000_000_000 => TimeUnit::Millis,
+            _ => TimeUnit::Seconds,
+        }
+    }
+    fn nanos(&self) -> u64 {
+        match self {
+            TimeUnit::Nanos => 1,
+            TimeUnit::Micros => 1_000,
+            TimeUnit::Millis => 1_000_000,
+            TimeUnit::Seconds => 1_000_000_000,
+        }
+    }
+    fn suffix(&self) -> &'static str {
+        match self {
+            TimeUnit::Nanos => "ns",
+            TimeUnit::Micros => "µs",
+            TimeUnit::Millis => "ms",
+            TimeUnit::Seconds => "s",
+        }
+    }
+    /// formats `nanos` in this unit, keeping about three significant figures
+    pub fn format(&self, nanos: u64) -> String {
+        let value = nanos as f64 / self.nanos() as f64;
+        let suffix = self.suffix();
+
+        match value {
+            100.0.. => format!("{value:.0}{suffix}"),
+            10.0.. => format!("{value:.1}{suffix}"),
+            _ if *self == TimeUnit::Nanos => format!("{value:.0}{suffix}"),
+            _ => format!("{value:.2}{suffix}"),
+        }
+    }
+}
+
+/// formats latency in the unit fitting it best
+pub fn format_latency(nanos: u64) -> String {
+    TimeUnit::fitting(nanos).format(nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        benchmark::{Failure, Sample},
+        fixtures::{failure, sample},
+    };
+
+    /// the histogram keeps 3 significant figures by default
+    fn assert_near(value: u64, expected: u64) {
+        let error = value.abs_diff(expected) as f64 / expected as f64;
+        assert!(error < 0.001, "{value} isn't close to {expected}");
+    }
+
+    #[test]
+    fn percentiles_of_empty_histogram_are_zero() {
+        let histogram = LatencyHistogram::default();
+
+        assert_eq!(histogram.len(), 0);
+        assert_eq!(histogram.percentiles(), Percentiles::default());
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        for millis in 1..=1000 {
+            histogram.record(Duration::from_millis(millis));
+        }
+        let percentiles = histogram.percentiles();
+
+        assert_eq!(histogram.len(), 1000);
+        assert_near(percentiles.p10, 100_000_000);
+        assert_near(percentiles.p25, 250_000_000);
+        assert_near(percentiles.p50, 500_000_000);
+        assert_near(percentiles.p75, 750_000_000);
+        assert_near(percentiles.p90, 900_000_000);
+        assert_near(percentiles.p99, 990_000_000);
+        assert_near(percentiles.p999, 999_000_000);
+        assert_near(percentiles.max, 1_000_000_000);
+        assert_eq!(Statistic::P99.of(&percentiles), percentiles.p99);
+
+        histogram.reset();
+        assert_eq!(histogram.len(), 0);
+    }
+
+    #[test]
+    fn latency_too_long_for_histogram_is_recorded() {
+        let mut histogram = LatencyHistogram::new(MAX_PRECISION + 1);
+        histogram.record(Duration::from_millis(1));
+        histogram.record(Duration::MAX);
+
+        assert_eq!(histogram.len(), 2);
+        assert!(histogram.percentiles().max > 1_000_000_000);
+    }
+
+    #[test]
+    fn interval_counts_requests_errors_phases_and_metrics() {
+        let mut stats = IntervalStats::default();
+        stats.record(&Outcome::Success(sample(10)));
+        stats.record(&Outcome::Success(sample(20)));
+        stats.record(&Outcome::Failure(failure(ErrorKind::Timeout)));
+        stats.record(&Outcome::Success(Sample {
+            metrics: vec![("pool".to_owned(), Metric::Gauge(4.0))],
+            phases: Vec::new(),
+            ..sample(40)
+        }));
+        let interval = stats.finish();
+
+        assert_eq!(interval.requests, 4);
+        assert_eq!(interval.errors.total(), 1);
+        assert_eq!(interval.error_rate(), 25.0);
+        assert_near(interval.percentiles.max, 40_000_000);
+        assert_near(interval.phase("connected").unwrap().max, 1_000_000);
+        assert_eq!(
+            interval.metrics,
+            vec![
+                ("bytes".to_owned(), Metric::Counter(200)),
+                ("pool".to_owned(), Metric::Gauge(3.0)),
+            ]
+        );
+        assert!(!interval.warmup);
+
+        // counters are still charted without reports, gauges and phases aren't
+        let interval = stats.finish();
+        assert_eq!(interval.requests, 0);
+        assert!(interval.phases.is_empty());
+        assert_eq!(
+            interval.metrics,
+            vec![("bytes".to_owned(), Metric::Counter(0))]
+        );
+    }
+
+    #[test]
+    fn interval_duration_leaves_out_pauses() {
+        let mut stats = IntervalStats::default();
+        let started = stats.started;
+        stats.pause_at(started + Duration::from_millis(50));
+        stats.resume_at(started + Duration::from_millis(250));
+        let interval = stats.finish_at(started + Duration::from_millis(300));
+
+        assert_eq!(interval.duration, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn interval_finished_while_paused_ends_at_pause() {
+        let mut stats = IntervalStats::default();
+        let started = stats.started;
+        stats.pause_at(started + Duration::from_millis(50));
+        let interval = stats.finish_at(started + Duration::from_millis(150));
+        assert_eq!(interval.duration, Duration::from_millis(50));
+
+        // the next interval starts paused too
+        stats.resume_at(started + Duration::from_millis(250));
+        let interval = stats.finish_at(started + Duration::from_millis(300));
+        assert_eq!(interval.duration, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn summary_leaves_out_warmup() {
+        let mut stats = IntervalStats::default();
+        stats.record(&Outcome::Success(Sample {
+            warmup: true,
+            ..sample(1000)
+        }));
+        stats.record(&Outcome::Failure(Failure {
+            warmup: true,
+            ..failure(ErrorKind::ConnectRefused)
+        }));
+        stats.record(&Outcome::Success(sample(10)));
+        stats.record(&Outcome::Failure(failure(ErrorKind::Timeout)));
+        let interval = stats.finish();
+        let summary = stats.summary();
+
+        assert!(interval.warmup);
+        assert_eq!(interval.requests, 4);
+        assert_eq!(summary.requests, 2);
+        assert_eq!(
+            summary.errors.iter().collect::<Vec<_>>(),
+            [(ErrorKind::Timeout, 1)]
+        );
+        assert_near(summary.percentiles.max, 10_000_000);
+        assert_eq!(
+            summary.metrics,
+            vec![("bytes".to_owned(), Metric::Counter(100))]
+        );
+        assert!(summary.duration <= interval.duration);
+
+        stats.record(&Outcome::Success(sample(20)));
+        let interval = stats.finish();
+        assert!(!interval.warmup);
+        assert_eq!(stats.summary().requests, 3);
+    }
+
+    #[test]
+    fn throughput() {
+        let interval = Interval {
+            requests: 10,
+            ..Interval::default()
+        };
+
+        assert_eq!(interval.throughput(), 0.0);
+        assert_eq!(
+            Interval {
+                duration: Duration::from_secs(2),
+                ..interval
+            }
+            .throughput(),
+            5.0
+        );
+    }
+
+    #[test]
+    fn time_unit_fitting() {
+        assert_eq!(TimeUnit::fitting(0), TimeUnit::Nanos);
+        assert_eq!(TimeUnit::fitting(999), TimeUnit::Nanos);
+        assert_eq!(TimeUnit::fitting(1_000), TimeUnit::Micros);
+        assert_eq!(TimeUnit::fitting(999_999), TimeUnit::Micros);
+        assert_eq!(TimeUnit::fitting(1_000_000), TimeUnit::Millis);
+        assert_eq!(TimeUnit::fitting(1_000_000_000), TimeUnit::Seconds);
+        assert_eq!(TimeUnit::fitting(u64::MAX), TimeUnit::Seconds);
+    }
+
+    #[test]
+    fn time_unit_format() {
+        assert_eq!(format_latency(5), "5ns");
+        assert_eq!(format_latency(999), "999ns");
+        assert_eq!(format_latency(1_500), "1.50µs");
+        assert_eq!(format_latency(12_345_000), "12.3ms");
+        assert_eq!(format_latency(250_000_000), "250ms");
+        assert_eq!(format_latency(2_000_000_000), "2.00s");
+        // the unit of the axis can be smaller than the value
+        assert_eq!(TimeUnit::Millis.format(2_000_000_000), "2000ms");
+        assert_eq!(TimeUnit::Millis.format(500_000), "0.50ms");
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    fmt::Display,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+/// tested server, new connections rotate through its addresses
+#[derive(Clone, Debug)]
+pub struct Target {
+    /// name given by the user, none when the address was given directly
+    pub host: Option<Arc<str>>,
+    pub addresses: Vec<SocketAddr>,
+    next: Arc<AtomicUsize>,
+}
+
+impl Target {
+    /// `addresses` can't be empty
+    pub fn new(host: Option<&str>, addresses: Vec<SocketAddr>) -> Self {
+        assert!(!addresses.is_empty(), "target needs at least one address");
+
+        Target {
+            host: host.map(Arc::from),
+            addresses,
+            next: Arc::default(),
+        }
+    }
+    /// address for the next connection
+    pub fn next_address(&self) -> SocketAddr {
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+
+        self.addresses[next % self.addresses.len()]
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let addresses: Vec<_> = self.addresses.iter().map(SocketAddr::to_string).collect();
+
+        match &self.host {
+            Some(host) => write!(f, "{host} ({})", addresses.join(", ")),
+            None => write!(f, "{}", addresses.join(", ")),
+        }
+    }
+}
+
+/// letters, digits and hyphens separated by dots, e.g. `localhost` or `example.com`
+pub fn is_hostname(name: &str) -> bool {
+    let labels: Vec<_> = name.split('.').collect();
+
+    // numbers only are an ip address, even when it's a bad one
+    if labels.iter().all(|label| label.parse::<u64>().is_ok()) {
+        return false;
+    }
+
+    name.len() <= 253
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// addresses of the host in the order returned by the resolver, without duplicates
+pub async fn resolve(host: String) -> Result<Vec<IpAddr>, String> {
+    let addresses = tokio::net::lookup_host((host.as_str(), 0))
+        .await
+        .map_err(|err| err.to_string())?;
+
+    Ok(unique(addresses.map(|address| address.ip())))
+}
+
+/// `host:port` or `address:port`, hostnames are resolved right away
+pub fn parse(target: &str) -> Result<Target, String> {
+    if let Ok(address) = target.parse::<SocketAddr>() {
+        return Ok(Target::new(None, vec![address]));
+    }
+
+    let (host, port) = target
+        .rsplit_once(':')
+        .ok_or_else(|| format!("expected HOST:PORT, got `{target}`"))?;
+    let port: u16 = port.parse().map_err(|_| format!("bad port `{port}`"))?;
+
+    if host.contains(':') {
+        return Err(format!(
+            "bad address `{target}`, IPv6 goes in brackets, e.g. [::1]:80"
+        ));
+    }
+    if !is_hostname(host) {
+        return Err(format!("bad host name `{host}`"));
+    }
+
+    let addresses = (host, port)
+        .to_socket_addrs()
+        .map_err(|err| format!("can't resolve `{host}`: {err}"))?;
+    let addresses = unique(addresses.map(|address| address.ip()));
+
+    if addresses.is_empty() {
+        return Err(format!("`{host}` has no addresses"));
+    }
+
+    let addresses = addresses
+        .into_iter()
+        .map(|ip| SocketAddr::new(ip, port))
+        .collect();
+
+    Ok(Target::new(Some(host), addresses))
+}
+
+fn unique(addresses: impl Iterator<Item = IpAddr>) -> Vec<IpAddr> {
+    let mut unique = Vec::new();
+    for address in addresses {
+        if !unique.contains(&address) {
+            unique.push(address);
+        }
+    }
+
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hostnames() {
+        for name in ["localhost", "example.com", "a-b.c1.io", "1.example", "x"] {
+            assert!(is_hostname(name), "`{name}` wasn't accepted");
+        }
+        let long_label = "a".repeat(64);
+        let long_name = vec!["a".repeat(63); 4].join(".");
+        for name in [
+            "",
+            "127.0.0.1",
+            "999.1",
+            "example..com",
+            "example.com.",
+            "-example.com",
+            "example-.com",
+            "exa_mple.com",
+            "::1",
+            &long_label,
+            &long_name,
+        ] {
+            assert!(!is_hostname(name), "`{name}` was accepted");
+        }
+    }
+
+    #[test]
+    fn addresses() {
+        let target = parse("127.0.0.1:8080").unwrap();
+        assert_eq!(target.host, None);
+        assert_eq!(target.addresses, ["127.0.0.1:8080".parse().unwrap()]);
+        assert_eq!(target.to_string(), "127.0.0.1:8080");
+
+        let target = parse("[::1]:80").unwrap();
+        assert_eq!(target.addresses, ["[::1]:80".parse().unwrap()]);
+    }
+
+    #[test]
+    #[ignore = "uses the resolver of the system"]
+    fn hostname_is_resolved() {
+        let target = parse("localhost:8080").unwrap();
+
+        assert_eq!(target.host.as_deref(), Some("localhost"));
+        assert!(!target.addresses.is_empty());
+        assert!(
+            target
+                .addresses
+                .iter()
+                .all(|address| { address.ip().is_loopback() && address.port() == 8080 })
+        );
+        assert!(target.to_string().starts_with("localhost ("));
+    }
+
+    #[test]
+    fn bad_targets() {
+        assert_eq!(
+            parse("localhost").unwrap_err(),
+            "expected HOST:PORT, got `localhost`"
+        );
+        assert_eq!(parse("localhost:http").unwrap_err(), "bad port `http`");
+        assert_eq!(parse("localhost:70000").unwrap_err(), "bad port `70000`");
+        assert_eq!(
+            parse("::1:80").unwrap_err(),
+            "bad address `::1:80`, IPv6 goes in brackets, e.g. [::1]:80"
+        );
+        assert_eq!(
+            parse("300.1.1.1:80").unwrap_err(),
+            "bad host name `300.1.1.1`"
+        );
+        assert_eq!(parse("a_b:80").unwrap_err(), "bad host name `a_b`");
+    }
+
+    #[test]
+    fn addresses_rotate() {
+        let addresses: Vec<SocketAddr> = vec![
+            "127.0.0.1:80".parse().unwrap(),
+            "127.0.0.2:80".parse().unwrap(),
+        ];
+        let target = Target::new(None, addresses.clone());
+        let copy = target.clone();
+
+        assert_eq!(target.next_address(), addresses[0]);
+        assert_eq!(copy.next_address(), addresses[1]);
+        assert_eq!(target.next_address(), addresses[0]);
+    }
+}
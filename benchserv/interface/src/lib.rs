@@ -1,15 +1,175 @@
-use std::path::Path;
+// the abi_stable macros generate the `_Ref` type names and impls inside of consts
+#![allow(non_camel_case_types, non_local_definitions)]
+
+use std::{fmt::Display, io, net::SocketAddr, path::Path};
 
 use async_ffi::FfiFuture;
 
 use abi_stable::{
     StableAbi, declare_root_module_statics,
-    library::{LibraryError, RootModule},
+    library::{LibraryError, RootModule, lib_header_from_path},
     package_version_strings, sabi_trait,
     sabi_types::VersionStrings,
-    std_types::{RBox, Tuple2},
+    std_types::{RBox, ROption, RSlice, RStr, Tuple2},
 };
 
+/// category of a failed request
+#[repr(u8)]
+#[derive(StableAbi, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    ConnectRefused,
+    Timeout,
+    Reset,
+    Protocol,
+    /// anything else, described by the error message
+    Custom,
+}
+
+impl From<&io::Error> for ErrorKind {
+    fn from(err: &io::Error) -> Self {
+        use io::ErrorKind::*;
+
+        match err.kind() {
+            ConnectionRefused => ErrorKind::ConnectRefused,
+            TimedOut => ErrorKind::Timeout,
+            ConnectionReset | ConnectionAborted | BrokenPipe => ErrorKind::Reset,
+            UnexpectedEof | InvalidData => ErrorKind::Protocol,
+            _ => ErrorKind::Custom,
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ErrorKind::ConnectRefused => "connection refused",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Reset => "connection reset",
+            ErrorKind::Protocol => "protocol error",
+            ErrorKind::Custom => "other",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// type of the plugin parameter value
+#[repr(u8)]
+#[derive(StableAbi, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterKind {
+    String,
+    Integer,
+    Float,
+    Bool,
+}
+
+impl ParameterKind {
+    /// whether the text typed by the user can be parsed as this kind
+    pub fn is_valid(&self, value: &str) -> bool {
+        match self {
+            ParameterKind::String => true,
+            ParameterKind::Integer => value.parse::<i64>().is_ok(),
+            ParameterKind::Float => value.parse::<f64>().is_ok(),
+            ParameterKind::Bool => value.parse::<bool>().is_ok(),
+        }
+    }
+}
+
+/// setting declared by the plugin, the app shows a form field for every one
+/// and passes the chosen values to the connections
+#[repr(C)]
+#[derive(StableAbi, Debug, Clone, Copy)]
+pub struct Parameter {
+    pub name: RStr<'static>,
+    pub kind: ParameterKind,
+    /// value used when the user doesn't change it
+    pub default: RStr<'static>,
+    pub description: RStr<'static>,
+}
+
+impl Parameter {
+    pub const fn new(
+        name: &'static str,
+        kind: ParameterKind,
+        default: &'static str,
+        description: &'static str,
+    ) -> Self {
+        Parameter {
+            name: RStr::from_str(name),
+            kind,
+            default: RStr::from_str(default),
+            description: RStr::from_str(description),
+        }
+    }
+}
+
+/// address families the plugin can connect to
+#[repr(C)]
+#[derive(StableAbi, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressFamilies {
+    pub ipv4: bool,
+    pub ipv6: bool,
+}
+
+impl AddressFamilies {
+    pub const IPV4: AddressFamilies = AddressFamilies {
+        ipv4: true,
+        ipv6: false,
+    };
+    pub const ALL: AddressFamilies = AddressFamilies {
+        ipv4: true,
+        ipv6: true,
+    };
+}
+
+impl Default for AddressFamilies {
+    /// plugins written before the families were declared only use `ip_v4`
+    fn default() -> Self {
+        AddressFamilies::IPV4
+    }
+}
+
+impl Display for AddressFamilies {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.ipv4, self.ipv6) {
+            (true, true) => write!(f, "IPv4, IPv6"),
+            (true, false) => write!(f, "IPv4"),
+            (false, true) => write!(f, "IPv6"),
+            (false, false) => write!(f, "none"),
+        }
+    }
+}
+
+/// information about the plugin shown in the plugin picker
+#[repr(C)]
+#[derive(StableAbi, Debug, Clone, Copy, Default)]
+pub struct Metadata {
+    /// name shown instead of the file name
+    pub name: RStr<'static>,
+    pub description: RStr<'static>,
+    pub author: RStr<'static>,
+    pub version: RStr<'static>,
+    pub families: AddressFamilies,
+}
+
+impl Metadata {
+    pub const fn new(
+        name: &'static str,
+        description: &'static str,
+        author: &'static str,
+        version: &'static str,
+        families: AddressFamilies,
+    ) -> Self {
+        Metadata {
+            name: RStr::from_str(name),
+            description: RStr::from_str(description),
+            author: RStr::from_str(author),
+            version: RStr::from_str(version),
+            families,
+        }
+    }
+}
+
 #[sabi_trait]
 pub trait ConnectionTimer: Send + Sync {
     /// start counting the time
@@ -17,7 +177,81 @@ pub trait ConnectionTimer: Send + Sync {
     /// stop counting the time and send latency to app
     fn stop(&self);
     fn ip_v4(&self) -> Tuple2<[u8; 4], u16>;
+    #[sabi(last_prefix_field)]
     fn ip_v6(&self) -> Tuple2<[u8; 16], u16>;
+    /// report that the request failed instead of calling `stop`
+    fn fail(&self, kind: ErrorKind, message: RStr<'_>);
+    /// end the current phase of the request, e.g. `connected` or `first_byte`,
+    /// phases are measured from the previous mark and the last one ends on `stop`
+    fn mark(&mut self, name: RStr<'_>);
+    /// value of the parameter declared by the plugin, none if the app didn't set it
+    fn parameter(&self, name: RStr<'_>) -> ROption<RStr<'_>>;
+    /// add to the named counter of the request, e.g. `bytes_sent` or `retries`,
+    /// counters are summed and charted per second
+    fn count(&mut self, name: RStr<'_>, value: u64);
+    /// set the named gauge of the request, e.g. `pool_size`, gauges are averaged
+    fn gauge(&mut self, name: RStr<'_>, value: f64);
+    /// server name given by the user, e.g. for the `Host` header or SNI,
+    /// none when the target is an ip address
+    fn host(&self) -> ROption<RStr<'_>>;
+    /// address of the server of either family, prefer it over `ip_v4` and `ip_v6`
+    fn socket_addr(&self) -> SocketAddress;
+    /// id of the test the connection belongs to, the one given to `on_test_start`
+    fn test(&self) -> u64;
+}
+
+/// `std::net::SocketAddr` that can cross the library boundary
+#[repr(u8)]
+#[derive(StableAbi, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketAddress {
+    V4(Tuple2<[u8; 4], u16>),
+    V6(Tuple2<[u8; 16], u16>),
+}
+
+impl From<SocketAddr> for SocketAddress {
+    fn from(address: SocketAddr) -> Self {
+        match address {
+            SocketAddr::V4(address) => {
+                SocketAddress::V4(Tuple2(address.ip().octets(), address.port()))
+            }
+            SocketAddr::V6(address) => {
+                SocketAddress::V6(Tuple2(address.ip().octets(), address.port()))
+            }
+        }
+    }
+}
+
+impl From<SocketAddress> for SocketAddr {
+    fn from(address: SocketAddress) -> Self {
+        match address {
+            SocketAddress::V4(Tuple2(ip, port)) => SocketAddr::new(ip.into(), port),
+            SocketAddress::V6(Tuple2(ip, port)) => SocketAddr::new(ip.into(), port),
+        }
+    }
+}
+
+/// settings of the test passed to `on_test_start`
+#[repr(C)]
+#[derive(StableAbi, Debug, Clone, Copy)]
+pub struct TestInfo<'a> {
+    /// tells apart the tests running at the same time, e.g. the compared
+    /// targets, state kept for the test should be looked up by it
+    pub test: u64,
+    /// addresses of the tested server, connections rotate through them
+    pub addresses: RSlice<'a, SocketAddress>,
+    /// server name given by the user, none when the target is an ip address
+    pub host: ROption<RStr<'a>>,
+    /// values of the plugin parameters chosen for the test
+    pub parameters: RSlice<'a, Tuple2<RStr<'a>, RStr<'a>>>,
+}
+
+impl<'a> TestInfo<'a> {
+    pub fn parameter(&self, name: &str) -> Option<RStr<'a>> {
+        self.parameters
+            .iter()
+            .find(|parameter| parameter.0 == name)
+            .map(|parameter| parameter.1)
+    }
 }
 
 pub type ConnectionTimerBox = ConnectionTimer_TO<'static, RBox<()>>;
@@ -31,6 +265,18 @@ pub struct ConnectionPlugin {
     /// this function is directly spawned onto a tokio runtime and should use the
     /// ConnectionTimerBox methods to measure the latency and show it on chart
     pub run_connection: extern "C" fn(ConnectionTimerBox) -> FfiFuture<()>,
+    /// parameters read by the plugin with `ConnectionTimer::parameter`
+    #[sabi(missing_field(default))]
+    pub parameters: RSlice<'static, Parameter>,
+    #[sabi(missing_field(default))]
+    pub metadata: Metadata,
+    /// called before the first connection of every test
+    #[sabi(missing_field(default))]
+    pub on_test_start: Option<extern "C" fn(&TestInfo<'_>)>,
+    /// called with the test id once the test is stopped and no more
+    /// connections are started
+    #[sabi(missing_field(default))]
+    pub on_test_end: Option<extern "C" fn(u64)>,
 }
 
 impl RootModule for ConnectionPlugin_Ref {
@@ -41,6 +287,8 @@ impl RootModule for ConnectionPlugin_Ref {
     const VERSION_STRINGS: VersionStrings = package_version_strings!();
 }
 
+/// `RootModule::load_from_file` keeps the first loaded module for the whole process,
+/// this loads the plugin at every path separately
 pub fn load_root_module_from_file(path: &Path) -> Result<ConnectionPlugin_Ref, LibraryError> {
-    ConnectionPlugin_Ref::load_from_file(path)
+    lib_header_from_path(path)?.init_root_module::<ConnectionPlugin_Ref>()
 }
@@ -1,54 +1,87 @@
+use abi_stable::std_types::RSlice;
 use abi_stable::{export_root_module, prefix_type::PrefixTypeTrait, sabi_extern_fn};
 use async_ffi::async_ffi;
 use async_std::{net::TcpStream, prelude::*};
-use interface::{ConnectionPlugin, ConnectionPlugin_Ref, ConnectionTimerBox};
-use std::{net::SocketAddr, time::Duration};
+use interface::{
+    AddressFamilies, ConnectionPlugin, ConnectionPlugin_Ref, ConnectionTimerBox, Metadata,
+    Parameter, ParameterKind,
+};
+use std::{io, net::SocketAddr};
 
 #[export_root_module]
 pub fn get_library() -> ConnectionPlugin_Ref {
-    ConnectionPlugin { run_connection }.leak_into_prefix()
+    ConnectionPlugin {
+        run_connection,
+        parameters: RSlice::from_slice(PARAMETERS),
+        metadata: Metadata::new(
+            "Basic TCP",
+            "sends a length-prefixed payload over a new TCP connection and reads the response",
+            "benchserv",
+            env!("CARGO_PKG_VERSION"),
+            AddressFamilies::ALL,
+        ),
+        on_test_start: None,
+        on_test_end: None,
+    }
+    .leak_into_prefix()
 }
 
+const PARAMETERS: &[Parameter] = &[Parameter::new(
+    "payload_size",
+    ParameterKind::Integer,
+    "1024",
+    "bytes sent to the server in every request",
+)];
+
 #[async_ffi]
 #[sabi_extern_fn]
 pub async fn run_connection(mut connection_timer: ConnectionTimerBox) {
-    let (ip, port) = connection_timer.ip_v4().into_tuple();
-    let ip = SocketAddr::new(ip.into(), port);
+    let ip: SocketAddr = connection_timer.socket_addr().into();
+    let payload_size = connection_timer
+        .parameter("payload_size".into())
+        .into_option()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(1024);
 
     // start counting time
     connection_timer.start();
 
-    let mut socket = None;
-    for _ in 0.. {
-        match TcpStream::connect(ip).await {
-            Ok(stream) => {
-                socket = Some(stream);
-                break;
-            }
-            Err(err) => async_std::task::sleep(Duration::from_millis(10)).await,
-        }
+    match request(ip, payload_size, &mut connection_timer).await {
+        // stop counting time - send to chart
+        Ok(()) => connection_timer.stop(),
+        Err(err) => connection_timer.fail((&err).into(), err.to_string().as_str().into()),
     }
+}
+
+async fn request(
+    ip: SocketAddr,
+    payload_size: usize,
+    connection_timer: &mut ConnectionTimerBox,
+) -> io::Result<()> {
+    let mut socket = TcpStream::connect(ip).await?;
+    connection_timer.mark("connected".into());
 
-    let mut socket = socket.unwrap();
-    let buffer = vec![1; 1024];
+    let buffer = vec![1; payload_size];
 
     // header
-    socket
-        .write_all(&buffer.len().to_le_bytes())
-        .await
-        .expect("server crashed");
+    socket.write_all(&buffer.len().to_le_bytes()).await?;
 
     // content
-    socket.write_all(&buffer).await.expect("server crashed");
+    socket.write_all(&buffer).await?;
+    connection_timer.count(
+        "bytes_sent".into(),
+        (size_of::<usize>() + payload_size) as u64,
+    );
 
     // response
     let mut header = [0u8; 8];
-    socket.read_exact(&mut header).await.expect("crash");
+    socket.read_exact(&mut header).await?;
+    connection_timer.mark("first_byte".into());
 
     let len = usize::from_le_bytes(header);
-    let mut buffer = Vec::with_capacity(len);
-    socket.read_exact(&mut buffer).await.expect("crash");
+    let mut buffer = vec![0; len];
+    socket.read_exact(&mut buffer).await?;
+    connection_timer.count("bytes_received".into(), (size_of::<usize>() + len) as u64);
 
-    // stop counting time - send to chart
-    connection_timer.stop();
+    Ok(())
 }
@@ -1,10 +1,30 @@
-use abi_stable::{export_root_module, prefix_type::PrefixTypeTrait, sabi_extern_fn};
+use abi_stable::{
+    export_root_module, prefix_type::PrefixTypeTrait, sabi_extern_fn, std_types::RSlice,
+};
 use async_ffi::async_ffi;
-use interface::{ConnectionPlugin, ConnectionPlugin_Ref, ConnectionTimerBox};
+use interface::{
+    AddressFamilies, ConnectionPlugin, ConnectionPlugin_Ref, ConnectionTimerBox, Metadata,
+};
 
 #[export_root_module]
 pub fn get_library() -> ConnectionPlugin_Ref {
-    ConnectionPlugin { run_connection }.leak_into_prefix()
+    ConnectionPlugin {
+        run_connection,
+        // settings shown in the app, see `interface::Parameter`
+        parameters: RSlice::from_slice(&[]),
+        // shown in the plugin picker, see `interface::Metadata`
+        metadata: Metadata::new(
+            "Template",
+            "",
+            "",
+            env!("CARGO_PKG_VERSION"),
+            AddressFamilies::IPV4,
+        ),
+        // optional setup and teardown of the state shared by the connections
+        on_test_start: None,
+        on_test_end: None,
+    }
+    .leak_into_prefix()
 }
 
 #[async_ffi]
@@ -1,34 +1,134 @@
-use abi_stable::{export_root_module, prefix_type::PrefixTypeTrait, sabi_extern_fn};
+use abi_stable::{
+    export_root_module, prefix_type::PrefixTypeTrait, sabi_extern_fn, std_types::RSlice,
+};
 use async_compat::Compat;
 use async_ffi::async_ffi;
-use interface::{ConnectionPlugin, ConnectionPlugin_Ref, ConnectionTimerBox};
-use reqwest::Client;
-use std::{net::SocketAddr, sync::LazyLock};
+use interface::{
+    AddressFamilies, ConnectionPlugin, ConnectionPlugin_Ref, ConnectionTimerBox, ErrorKind,
+    Metadata, Parameter, ParameterKind, TestInfo,
+};
+use reqwest::{Client, header::HOST};
+use std::{collections::BTreeMap, error::Error, net::SocketAddr, sync::Mutex};
 
 #[export_root_module]
 pub fn get_library() -> ConnectionPlugin_Ref {
-    ConnectionPlugin { run_connection }.leak_into_prefix()
+    ConnectionPlugin {
+        run_connection,
+        parameters: RSlice::from_slice(PARAMETERS),
+        metadata: Metadata::new(
+            "Web",
+            "sends HTTP GET requests and reads the whole response",
+            "benchserv",
+            env!("CARGO_PKG_VERSION"),
+            AddressFamilies::ALL,
+        ),
+        on_test_start: Some(on_test_start),
+        on_test_end: Some(on_test_end),
+    }
+    .leak_into_prefix()
 }
 
-static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| Client::new());
+const PARAMETERS: &[Parameter] = &[Parameter::new(
+    "path",
+    ParameterKind::String,
+    "/",
+    "path of the requested url",
+)];
+
+/// clients shared by the connections of each running test, every test starts
+/// with an empty connection pool
+static CLIENTS: Mutex<BTreeMap<u64, Client>> = Mutex::new(BTreeMap::new());
+
+#[sabi_extern_fn]
+pub fn on_test_start(info: &TestInfo<'_>) {
+    CLIENTS.lock().unwrap().insert(info.test, Client::new());
+}
+
+#[sabi_extern_fn]
+pub fn on_test_end(test: u64) {
+    // closes the pooled connections
+    CLIENTS.lock().unwrap().remove(&test);
+}
 
 #[async_ffi]
 #[sabi_extern_fn]
 pub async fn run_connection(mut connection_timer: ConnectionTimerBox) {
-    let (ip, port) = connection_timer.ip_v4().into_tuple();
-    let ip = SocketAddr::new(ip.into(), port).to_string();
+    let ip: SocketAddr = connection_timer.socket_addr().into();
+    let port = ip.port();
+    let path = connection_timer
+        .parameter("path".into())
+        .into_option()
+        .map_or("/".to_owned(), |path| path.to_string());
+    // connect to the resolved address but ask for the name given by the user
+    let host = connection_timer
+        .host()
+        .into_option()
+        .map(|host| match port {
+            80 => host.to_string(),
+            _ => format!("{host}:{port}"),
+        });
+
+    let client = CLIENTS
+        .lock()
+        .unwrap()
+        .get(&connection_timer.test())
+        .cloned();
+    let Some(client) = client else {
+        let message = "no HTTP client for the test, `on_test_start` wasn't called for it";
+        connection_timer.fail(ErrorKind::Custom, message.into());
+        return;
+    };
 
     // start counting time
     connection_timer.start();
 
     // compat is used because dynamic libraries don't see tokio runtime
-    Compat::new(async {
-        let request = CLIENT.get(format!("http://{ip}")).build().unwrap();
-        let response = CLIENT.execute(request).await.unwrap().text().await.unwrap();
+    let result = Compat::new(async {
+        let mut request = client.get(format!("http://{ip}{path}"));
+        if let Some(host) = host {
+            request = request.header(HOST, host);
+        }
 
-        //println!("{response}");
+        let response = request.send().await?;
+        // response headers arrived
+        connection_timer.mark("first_byte".into());
+
+        let status = format!("status_{}", response.status().as_u16());
+        connection_timer.count(status.as_str().into(), 1);
+
+        let body = response.error_for_status()?.bytes().await?;
+        connection_timer.count("bytes_received".into(), body.len() as u64);
+
+        Ok::<_, reqwest::Error>(())
     })
     .await;
 
-    connection_timer.stop();
+    match result {
+        Ok(()) => connection_timer.stop(),
+        Err(err) => connection_timer.fail(error_kind(&err), err.to_string().as_str().into()),
+    }
+}
+
+fn error_kind(err: &reqwest::Error) -> ErrorKind {
+    if err.is_timeout() {
+        return ErrorKind::Timeout;
+    }
+    if err.is_status() || err.is_decode() {
+        return ErrorKind::Protocol;
+    }
+
+    // look for the io error that caused the request to fail
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            return err.into();
+        }
+        source = err.source();
+    }
+
+    if err.is_connect() {
+        return ErrorKind::ConnectRefused;
+    }
+
+    ErrorKind::Custom
 }